@@ -1,3 +1,4 @@
+use tetra::time::Timestep;
 use tetra::ContextBuilder;
 
 mod prefabs;
@@ -6,14 +7,125 @@ mod systems;
 
 pub use systems::scenemanagement::SceneManager;
 
+use systems::config::Config;
+use systems::environment::{self, Environment};
+use systems::sprites::Sprites;
+use systems::{autopilot, reachability};
+
 pub const SCREEN_WIDTH: i32 = 288;
 pub const SCREEN_HEIGHT: i32 = 505;
-pub const GRAVITY: f32 = 9.1;
-pub const SCROLL_SPEED: f32 = 3.0;
+/// Downward acceleration of the bird, in pixels per second squared.
+pub const GRAVITY: f32 = 1092.0;
+/// Speed of the pipes and the ground, in pixels per second.
+pub const SCROLL_SPEED: f32 = 180.0;
+/// Simulation steps per second.
+pub const TICK_RATE: f32 = 60.0;
 pub const FILE_NAME: &str = "highscore.txt";
+pub const REPLAY_FILE_NAME: &str = "last-run.replay";
+pub const CONFIG_FILE_NAME: &str = "flappy.toml";
+pub const CAMPAIGN_FILE_NAME: &str = "campaign.txt";
+pub const LEVELS_DIRECTORY: &str = "./resources/levels";
+
+/// Seeds checked by `check-pipes` and `autopilot` unless a count is given.
+const DEFAULT_SEEDS_TO_CHECK: u64 = 1000;
 
 fn main() -> tetra::Result {
-    ContextBuilder::new("Flappy Bird", SCREEN_WIDTH, SCREEN_HEIGHT)
-        .build()?
-        .run(SceneManager::new)
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    let command = match args.first().map(String::as_str) {
+        Some("check-pipes") | Some("autopilot") | Some("demo") | Some("env") => {
+            Some(args.remove(0))
+        }
+        _ => None,
+    };
+    let mut seeds_to_check = DEFAULT_SEEDS_TO_CHECK;
+    if matches!(command.as_deref(), Some("check-pipes") | Some("autopilot")) {
+        if let Some(count) = args.first().and_then(|arg| arg.parse().ok()) {
+            seeds_to_check = count;
+            args.remove(0);
+        }
+    }
+
+    let config = match Config::load(args) {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("Invalid configuration: {}", err);
+            std::process::exit(1);
+        }
+    };
+
+    match command.as_deref() {
+        Some("check-pipes") => return check_pipe_placement(&config, seeds_to_check),
+        Some("autopilot") => return check_autopilot(&config, seeds_to_check),
+        Some("env") => return serve_environment(&config),
+        _ => {}
+    }
+
+    let demo = command.as_deref() == Some("demo");
+    ContextBuilder::new(
+        "Flappy Bird",
+        config.tuning.screen_width,
+        config.tuning.screen_height,
+    )
+    .timestep(Timestep::Variable)
+    // The wipe transition masks the new screen with a circle drawn to the stencil buffer.
+    .stencil_buffer(true)
+    .build()?
+    .run(|ctx| {
+        if demo {
+            SceneManager::demo(ctx, config)
+        } else {
+            SceneManager::new(ctx, config)
+        }
+    })
+}
+
+/// Makes sure every pipe layout for the first `count` seeds can be flown through.
+fn check_pipe_placement(config: &Config, count: u64) -> tetra::Result {
+    let failures = reachability::check_seeds(&config.tuning, &Sprites::load()?, 0..count);
+    if failures.is_empty() {
+        println!("All {} seeds are survivable", count);
+        return Ok(());
+    }
+
+    for seed in &failures {
+        println!("Found no way through seed {}", seed);
+    }
+    eprintln!("Found no way through {} of {} seeds", failures.len(), count);
+    std::process::exit(1);
+}
+
+/// Lets the autopilot fly the first `count` seeds, as a smoke test for pipe layouts that
+/// are survivable in theory but not in practice.
+fn check_autopilot(config: &Config, count: u64) -> tetra::Result {
+    let crashes = autopilot::check_seeds(&config.tuning, &Sprites::load()?, 0..count);
+    if crashes.is_empty() {
+        println!("The autopilot got through all {} seeds", count);
+        return Ok(());
+    }
+
+    for (seed, score) in &crashes {
+        println!(
+            "The autopilot crashed on seed {} with a score of {}",
+            seed, score
+        );
+    }
+    eprintln!(
+        "The autopilot crashed on {} of {} seeds",
+        crashes.len(),
+        count
+    );
+    std::process::exit(1);
+}
+
+/// Runs the game as an environment for training agents, driven through stdin and stdout.
+fn serve_environment(config: &Config) -> tetra::Result {
+    let environment = Environment::new(config.tuning.clone(), &Sprites::load()?);
+    let stdin = std::io::stdin();
+    let stdout = std::io::stdout();
+    if let Err(err) = environment::serve(environment, stdin.lock(), stdout.lock()) {
+        eprintln!("Environment stopped: {}", err);
+        std::process::exit(1);
+    }
+
+    Ok(())
 }
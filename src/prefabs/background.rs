@@ -1,8 +1,9 @@
 use tetra::graphics::Texture;
 use tetra::math::Vec2;
+use tetra::window;
 use tetra::Context;
 
-use crate::SCROLL_SPEED;
+use crate::systems::assets;
 
 pub struct Background {
     forest_texture: Texture,
@@ -19,41 +20,46 @@ pub struct Background {
 impl Background {
     pub fn new(ctx: &mut Context) -> tetra::Result<Background> {
         Ok(Background {
-            forest_texture: Texture::new(ctx, "./resources/trees.png")?,
+            forest_texture: assets::texture(ctx, "./resources/trees.png")?,
             forest_pos: 0.0,
 
-            cityscape_texture: Texture::new(ctx, "./resources/cityscape.png")?,
+            cityscape_texture: assets::texture(ctx, "./resources/cityscape.png")?,
             cityscape_pos: 0.0,
 
-            cloud_texture: Texture::new(ctx, "./resources/clouds.png")?,
+            cloud_texture: assets::texture(ctx, "./resources/clouds.png")?,
             cloud_pos: 0.0,
 
             scroll: true,
         })
     }
 
-    pub fn update(&mut self) {
+    /// Scrolls the layers at fractions of `scroll_speed`, the speed of the ground.
+    pub fn update(&mut self, scroll_speed: f32, dt: f32) {
         if self.scroll {
-            self.forest_pos = (self.forest_pos - SCROLL_SPEED * 0.75) % 335.0;
-            self.cityscape_pos = (self.cityscape_pos - SCROLL_SPEED * 0.5) % 300.0;
-            self.cloud_pos = (self.cloud_pos - SCROLL_SPEED * 0.25) % 352.0;
+            self.forest_pos = (self.forest_pos - scroll_speed * 0.75 * dt) % 335.0;
+            self.cityscape_pos = (self.cityscape_pos - scroll_speed * 0.5 * dt) % 300.0;
+            self.cloud_pos = (self.cloud_pos - scroll_speed * 0.25 * dt) % 352.0;
         }
     }
 
     pub fn draw(&mut self, ctx: &mut Context) {
-        self.cloud_texture
-            .draw(ctx, Vec2::new(self.cloud_pos, 300.0));
-        self.cloud_texture
-            .draw(ctx, Vec2::new(self.cloud_pos + 352.0, 300.0));
-
-        self.cityscape_texture
-            .draw(ctx, Vec2::new(self.cityscape_pos, 330.0));
-        self.cityscape_texture
-            .draw(ctx, Vec2::new(self.cityscape_pos + 300.0, 330.0));
-
-        self.forest_texture
-            .draw(ctx, Vec2::new(self.forest_pos, 360.0));
-        self.forest_texture
-            .draw(ctx, Vec2::new(self.forest_pos + 335.0, 360.0));
+        let width = window::get_width(ctx) as f32;
+        draw_tiled(ctx, &self.cloud_texture, self.cloud_pos, 300.0, width);
+        draw_tiled(
+            ctx,
+            &self.cityscape_texture,
+            self.cityscape_pos,
+            330.0,
+            width,
+        );
+        draw_tiled(ctx, &self.forest_texture, self.forest_pos, 360.0, width);
+    }
+}
+
+/// Repeats `texture` from `x` until it covers `width`.
+fn draw_tiled(ctx: &mut Context, texture: &Texture, mut x: f32, y: f32, width: f32) {
+    while x < width {
+        texture.draw(ctx, Vec2::new(x, y));
+        x += texture.width() as f32;
     }
 }
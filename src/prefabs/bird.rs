@@ -1,60 +1,141 @@
-use std::time::Duration;
+use std::sync::Arc;
+
+use serde::Deserialize;
 use tetra::graphics::animation::Animation;
-use tetra::graphics::{DrawParams, Rectangle, Texture};
+use tetra::graphics::{Color, DrawParams, Rectangle};
 use tetra::math::Vec2;
 use tetra::Context;
 
-use crate::systems::physics::{check_collision, PhysicsBody};
-use crate::{GRAVITY, SCREEN_HEIGHT};
+use crate::systems::physics::{CollisionMask, PhysicsBody, PlacedMask, Shape};
+use crate::systems::tuning::Tuning;
+
+const GHOST_COLOR: Color = Color::rgba(1.0, 1.0, 1.0, 0.4);
+
+/// Distance of the bird from the left edge of the screen.
+pub const BIRD_X: f32 = 100.0;
+/// Half the width of a frame of the flapping animation.
+pub const BIRD_HALF_WIDTH: f32 = 17.0;
+/// Height of a frame of the flapping animation.
+pub const BIRD_HEIGHT: f32 = 24.0;
+/// The point of a frame the bird is placed and rotated by, in the middle of its body.
+pub const BIRD_ORIGIN: Vec2<f32> = Vec2 {
+    x: BIRD_HALF_WIDTH,
+    y: BIRD_HEIGHT / 2.0,
+};
+/// How long the bird takes to tilt upwards after a flap, in seconds.
+const FLAP_TWEEN_DURATION: f32 = 0.1;
+/// How fast the bird tilts back down, in radians per second.
+const DIVE_ROTATION_SPEED: f32 = 3.0;
+/// How long each frame of the flapping animation is shown, in seconds.
+pub const FRAME_LENGTH: f32 = 0.1;
+
+/// What the bird collides with.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Hitbox {
+    /// The solid pixels of the current animation frame.
+    Mask,
+    /// A circle around the center of the sprite.
+    Circle,
+    /// A capsule along the bird's body, tilting with it.
+    Capsule,
+    /// A rectangle slightly smaller than the sprite, tilting with it.
+    Box,
+}
+
+/// Where the bird was and how it was tilted on a single frame.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Pose {
+    pub position: Vec2<f32>,
+    pub rotation: f32,
+}
+
+impl Pose {
+    pub fn lerp(from: Pose, to: Pose, t: f32) -> Pose {
+        Pose {
+            position: Vec2::lerp(from.position, to.position, t),
+            rotation: from.rotation + (to.rotation - from.rotation) * t,
+        }
+    }
+}
 
 pub struct Bird {
-    animation: Animation,
     rotation: f32,
     velocity: Vec2<f32>,
-    flap_counter: i32,
-    flap_delta: f64,
+    flap_timer: f32,
+    flap_delta: f32,
+    previous: Pose,
+    animation_time: f32,
+    masks: Vec<Arc<CollisionMask>>,
+
+    start_position: Vec2<f32>,
+    flap_speed: f32,
 
     pub position: Vec2<f32>,
+    pub hitbox: Hitbox,
     pub allow_gravity: bool,
     pub alive: bool,
 }
 
 impl PhysicsBody for Bird {
     fn get_collision_rect(&mut self) -> Rectangle {
-        Rectangle::new(self.position.x, self.position.y, 34.0, 24.0)
+        self.get_shapes()[0].bounds()
     }
 
-    fn collides_with(&mut self, obj: &Rectangle) -> bool {
-        check_collision(&self.get_collision_rect(), obj)
+    fn get_shapes(&mut self) -> Vec<Shape> {
+        self.shapes_at(self.pose())
     }
 }
 
 impl Bird {
-    pub fn new(ctx: &mut Context) -> tetra::Result<Bird> {
-        Ok(Bird {
-            animation: Animation::new(
-                Texture::new(ctx, "./resources/bird.png")?,
-                Rectangle::row(0.0, 0.0, 34.0, 24.0).take(3).collect(),
-                Duration::from_secs_f64(0.1),
-            ),
+    /// Creates a bird colliding with `masks`, one per frame of the flapping animation.
+    pub fn new(tuning: &Tuning, masks: Vec<Arc<CollisionMask>>) -> Bird {
+        let position = Vec2::new(BIRD_X, tuning.screen_height as f32 / 2.0);
+
+        Bird {
             rotation: 0.0,
-            position: Vec2::new(100.0, SCREEN_HEIGHT as f32 / 2.0),
+            position,
             velocity: Vec2::new(0.0, 0.0),
-            flap_counter: 0,
+            flap_timer: 0.0,
             flap_delta: 0.0,
+            previous: Pose {
+                position,
+                rotation: 0.0,
+            },
+            animation_time: 0.0,
+            masks,
+            start_position: position,
+            flap_speed: tuning.flap_speed,
+            hitbox: tuning.bird_hitbox,
             allow_gravity: false,
             alive: false,
-        })
+        }
+    }
+
+    /// The source rectangles of the flapping animation in `bird.png`.
+    pub fn frames() -> Vec<Rectangle> {
+        Rectangle::row(0.0, 0.0, 34.0, BIRD_HEIGHT)
+            .take(3)
+            .collect()
+    }
+
+    /// The animation frame currently shown, which also picks the collision mask.
+    pub fn animation_frame(&self) -> usize {
+        (self.animation_time / FRAME_LENGTH) as usize % self.masks.len()
     }
 
     pub fn flap(&mut self) {
         if self.alive {
-            self.velocity.y = -6.5;
-            self.flap_counter = 6;
+            self.velocity.y = -self.flap_speed;
+            self.flap_timer = FLAP_TWEEN_DURATION;
             self.tween_rotation();
         }
     }
 
+    pub fn set_flap_speed(&mut self, flap_speed: f32) {
+        self.flap_speed = flap_speed;
+    }
+
     pub fn kill(&mut self) {
         if self.alive && self.velocity.y < 0.0 {
             self.velocity.y = 0.0;
@@ -64,47 +145,175 @@ impl Bird {
 
     pub fn reset(&mut self) {
         self.velocity = Vec2::new(0.0, 0.0);
-        self.position = Vec2::new(100.0, SCREEN_HEIGHT as f32 / 2.0);
+        self.position = self.start_position;
         self.rotation = 0.0;
+        self.flap_timer = 0.0;
         self.flap_delta = 0.0;
+        self.previous = self.pose();
+        self.animation_time = 0.0;
+        self.allow_gravity = false;
         self.alive = true;
     }
 
+    pub fn pose(&self) -> Pose {
+        Pose {
+            position: self.position,
+            rotation: self.rotation,
+        }
+    }
+
+    pub fn velocity(&self) -> Vec2<f32> {
+        self.velocity
+    }
+
+    /// The pose before the last update.
+    pub fn previous_pose(&self) -> Pose {
+        self.previous
+    }
+
+    /// Moves the bird back to `t` of the way through its last update, e.g. to a point of impact.
+    pub fn rewind(&mut self, t: f32) {
+        let pose = Pose::lerp(self.previous, self.pose(), t);
+        self.position = pose.position;
+        self.rotation = pose.rotation;
+    }
+
+    /// The shapes the bird would collide with if it were in `pose`.
+    pub fn shapes_at(&self, pose: Pose) -> Vec<Shape> {
+        let shape = match self.hitbox {
+            Hitbox::Mask => Shape::Mask(PlacedMask {
+                mask: self.masks[self.animation_frame()].clone(),
+                position: pose.position,
+                origin: BIRD_ORIGIN,
+                rotation: pose.rotation,
+                scale: Vec2::one(),
+            }),
+            Hitbox::Circle => Shape::Circle {
+                center: pose.position,
+                radius: 12.0,
+            },
+            Hitbox::Capsule => {
+                let (sin, cos) = pose.rotation.sin_cos();
+                let half_length = Vec2::new(cos, sin) * 7.0;
+                Shape::Capsule {
+                    start: pose.position - half_length,
+                    end: pose.position + half_length,
+                    radius: 10.0,
+                }
+            }
+            Hitbox::Box => Shape::Obb {
+                center: pose.position,
+                half_extents: Vec2::new(15.0, 10.0),
+                rotation: pose.rotation,
+            },
+        };
+
+        vec![shape]
+    }
+
     pub fn tween_rotation(&mut self) {
-        let distance = f64::from(-1.0 - self.rotation);
-        self.flap_delta = distance.abs() / f64::from(self.flap_counter);
+        let distance = -1.0 - self.rotation;
+        self.flap_delta = distance.abs() / self.flap_timer;
+    }
+
+    /// Moves the flapping animation on by `dt` seconds.
+    fn animate(&mut self, dt: f32) {
+        self.animation_time = (self.animation_time + dt) % (FRAME_LENGTH * self.masks.len() as f32);
     }
 
-    pub fn update(&mut self, _ctx: &mut Context) {
+    /// Advances the bird by `dt` seconds under `gravity`, in pixels per second squared.
+    pub fn update(&mut self, gravity: f32, dt: f32) {
+        self.previous = self.pose();
+        if self.alive {
+            self.animate(dt);
+        }
+
         if self.allow_gravity {
-            self.velocity.y += GRAVITY / 30.0;
-            self.position.y += self.velocity.y;
+            self.velocity.y += gravity * dt;
+            self.position.y += self.velocity.y * dt;
             if self.position.y <= 12.0 {
                 self.position.y = 12.0;
                 self.velocity.y = 0.0;
             }
 
-            if self.flap_counter > 0 {
-                self.rotation -= self.flap_delta as f32;
-                self.flap_counter -= 1;
+            if self.flap_timer > 0.0 {
+                self.rotation -= self.flap_delta * dt.min(self.flap_timer);
+                self.flap_timer -= dt;
             }
             if self.rotation < 1.5 {
-                self.rotation += 0.05;
+                self.rotation += DIVE_ROTATION_SPEED * dt;
             }
         }
     }
 
-    pub fn draw(&mut self, ctx: &mut Context) {
-        if self.alive {
-            self.animation.advance(ctx);
-        }
+    /// Draws the bird `blend` of the way from its previous pose to its current one.
+    pub fn draw(&self, ctx: &mut Context, animation: &mut Animation, blend: f32) {
+        self.draw_tinted(ctx, animation, blend, Color::WHITE);
+    }
 
-        self.animation.draw(
+    fn draw_tinted(&self, ctx: &mut Context, animation: &mut Animation, blend: f32, color: Color) {
+        animation.set_current_frame_index(self.animation_frame());
+        let pose = Pose::lerp(self.previous, self.pose(), blend);
+        animation.draw(
             ctx,
             DrawParams::new()
-                .position(self.position)
-                .origin(Vec2::new(17.0, 12.0))
-                .rotation(self.rotation),
+                .position(pose.position)
+                .origin(BIRD_ORIGIN)
+                .rotation(pose.rotation)
+                .color(color),
         );
     }
 }
+
+/// A translucent bird retracing a recorded run, one pose per step. It never collides
+/// with anything.
+pub struct GhostBird {
+    bird: Bird,
+    trace: Vec<Pose>,
+    frame: usize,
+    tick_length: f32,
+}
+
+impl GhostBird {
+    /// Creates a ghost for a run recorded with `tuning`, sharing the bird's `masks`.
+    pub fn new(trace: Vec<Pose>, tuning: &Tuning, masks: Vec<Arc<CollisionMask>>) -> GhostBird {
+        let mut ghost = GhostBird {
+            bird: Bird::new(tuning, masks),
+            trace,
+            frame: 0,
+            tick_length: tuning.tick_length(),
+        };
+        ghost.reset();
+        ghost
+    }
+
+    pub fn reset(&mut self) {
+        self.bird.reset();
+        self.frame = 0;
+    }
+
+    pub fn update(&mut self) {
+        match self.trace.get(self.frame) {
+            Some(&pose) => {
+                self.bird.previous = self.bird.pose();
+                self.bird.position = pose.position;
+                self.bird.rotation = pose.rotation;
+                // Flap along with the recorded steps rather than the clock.
+                if self.frame > 0 {
+                    self.bird.animate(self.tick_length);
+                }
+                self.frame += 1;
+            }
+            None => {
+                self.bird.previous = self.bird.pose();
+                self.bird.alive = false;
+            }
+        }
+    }
+
+    pub fn draw(&self, ctx: &mut Context, animation: &mut Animation, blend: f32) {
+        if !self.trace.is_empty() {
+            self.bird.draw_tinted(ctx, animation, blend, GHOST_COLOR);
+        }
+    }
+}
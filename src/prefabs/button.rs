@@ -1,15 +1,25 @@
-use tetra::graphics::{Rectangle, Texture};
+use tetra::graphics::text::Text;
+use tetra::graphics::{Color, DrawParams, Rectangle, Texture};
 use tetra::math::Vec2;
 use tetra::Context;
 
+use crate::systems::assets;
+
+enum Face {
+    Image(Texture),
+    Label(Text),
+}
+
 pub struct Button {
-    texture: Texture,
+    face: Face,
     rect: Rectangle,
+    /// Disabled buttons are drawn faded and can't be pressed.
+    pub enabled: bool,
 }
 
 impl Button {
     pub fn new(ctx: &mut Context, centered_position: Vec2<f32>) -> tetra::Result<Button> {
-        let texture = Texture::new(ctx, "./resources/start-button.png")?;
+        let texture = assets::texture(ctx, "./resources/start-button.png")?;
         let rect = Rectangle::new(
             centered_position.x - texture.width() as f32 / 2.0,
             centered_position.y - texture.height() as f32 / 2.0,
@@ -17,17 +27,56 @@ impl Button {
             texture.height() as f32,
         );
 
-        Ok(Button { texture, rect })
+        Ok(Button {
+            face: Face::Image(texture),
+            rect,
+            enabled: true,
+        })
+    }
+
+    pub fn with_label(
+        ctx: &mut Context,
+        centered_position: Vec2<f32>,
+        label: &str,
+    ) -> tetra::Result<Button> {
+        let mut text = Text::new(
+            label,
+            assets::font(ctx, "./resources/font/flappy-font.ttf", 20.0)?,
+        );
+        let bounds = text.get_bounds(ctx).unwrap_or_default();
+        let rect = Rectangle::new(
+            centered_position.x - bounds.width / 2.0,
+            centered_position.y - bounds.height / 2.0,
+            bounds.width,
+            bounds.height,
+        );
+
+        Ok(Button {
+            face: Face::Label(text),
+            rect,
+            enabled: true,
+        })
     }
 
     pub fn contains(&mut self, point: Vec2<f32>) -> bool {
-        point.x >= self.rect.x
+        self.enabled
+            && point.x >= self.rect.x
             && point.x <= (self.rect.x + self.rect.width)
             && point.y >= self.rect.y
             && point.y <= (self.rect.y + self.rect.height)
     }
 
     pub fn draw(&mut self, ctx: &mut Context) {
-        self.texture.draw(ctx, Vec2::new(self.rect.x, self.rect.y));
+        let params = DrawParams::new()
+            .position(Vec2::new(self.rect.x, self.rect.y))
+            .color(if self.enabled {
+                Color::WHITE
+            } else {
+                Color::rgba(1.0, 1.0, 1.0, 0.4)
+            });
+        match &mut self.face {
+            Face::Image(texture) => texture.draw(ctx, params),
+            Face::Label(text) => text.draw(ctx, params),
+        }
     }
 }
@@ -0,0 +1,113 @@
+use tetra::graphics::mesh::GeometryBuilder;
+use tetra::graphics::text::Text;
+use tetra::graphics::{Color, DrawParams};
+use tetra::input::{self, Key};
+use tetra::math::Vec2;
+use tetra::window;
+use tetra::Context;
+
+use crate::systems::assets;
+use crate::systems::physics::{PhysicsBody, Shape};
+use crate::systems::simulation::Simulation;
+
+const RECT_COLOR: Color = Color::rgb(1.0, 1.0, 0.0);
+const SHAPE_COLOR: Color = Color::rgb(1.0, 0.0, 0.0);
+const SCORING_LINE_COLOR: Color = Color::rgb(0.0, 1.0, 1.0);
+
+/// Outlines what the simulation collides with, toggled with F3. Drawn at the latest
+/// simulation step rather than interpolated, so it shows exactly what was tested.
+pub struct DebugOverlay {
+    visible: bool,
+    readout: Text,
+}
+
+impl DebugOverlay {
+    pub fn new(ctx: &mut Context) -> tetra::Result<DebugOverlay> {
+        Ok(DebugOverlay {
+            visible: false,
+            readout: Text::new(
+                "",
+                assets::font(ctx, "./resources/font/flappy-font.ttf", 12.0)?,
+            ),
+        })
+    }
+
+    pub fn update(&mut self, ctx: &mut Context) {
+        if input::is_key_pressed(ctx, Key::F3) {
+            self.visible = !self.visible;
+        }
+    }
+
+    pub fn draw(&mut self, ctx: &mut Context, simulation: &mut Simulation) -> tetra::Result {
+        if !self.visible {
+            return Ok(());
+        }
+
+        let mut rects = GeometryBuilder::new();
+        rects.set_color(RECT_COLOR);
+        let mut shapes = GeometryBuilder::new();
+        shapes.set_color(SHAPE_COLOR);
+        let mut scoring_lines = GeometryBuilder::new();
+        scoring_lines.set_color(SCORING_LINE_COLOR);
+
+        outline_body(&mut rects, &mut shapes, &mut simulation.bird)?;
+        outline_body(&mut rects, &mut shapes, &mut simulation.ground)?;
+
+        let height = window::get_height(ctx) as f32;
+        for pipe_group in simulation.pipes.iter_mut().filter(|group| group.alive) {
+            for pipe in pipe_group.pipes_mut() {
+                outline_body(&mut rects, &mut shapes, pipe)?;
+            }
+
+            let x = pipe_group.gap_center().x;
+            scoring_lines.polyline(1.0, &[Vec2::new(x, 0.0), Vec2::new(x, height)])?;
+        }
+
+        for builder in [rects, shapes, scoring_lines] {
+            // An empty builder can't be turned into a mesh.
+            if !builder.vertices().is_empty() {
+                builder.build_mesh(ctx)?.draw(ctx, Vec2::zero());
+            }
+        }
+
+        let bird = &simulation.bird;
+        let spawner = simulation.pipe_spawner();
+        self.readout.set_content(format!(
+            "frame {}\nvelocity {:.0}, {:.0}\nrotation {:.2}\nnext pipe {:.2} / {:.2}\nlevel {}",
+            simulation.frame(),
+            bird.velocity().x,
+            bird.velocity().y,
+            bird.pose().rotation,
+            spawner.elapsed(),
+            spawner.spawn_interval(),
+            simulation.level(),
+        ));
+        self.readout
+            .draw(ctx, DrawParams::new().position(Vec2::new(4.0, 4.0)));
+
+        Ok(())
+    }
+}
+
+fn outline_body(
+    rects: &mut GeometryBuilder,
+    shapes: &mut GeometryBuilder,
+    body: &mut dyn PhysicsBody,
+) -> tetra::Result {
+    outline(rects, &Shape::Aabb(body.get_collision_rect()))?;
+    for shape in body.get_shapes() {
+        outline(shapes, &shape)?;
+    }
+
+    Ok(())
+}
+
+fn outline(builder: &mut GeometryBuilder, shape: &Shape) -> tetra::Result {
+    let mut points = shape.outline();
+    if let Some(&first) = points.first() {
+        points.push(first);
+        builder.polyline(1.0, &points)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,46 @@
+use tetra::graphics::mesh::{GeometryBuilder, Mesh, ShapeStyle};
+use tetra::graphics::{Color, Rectangle};
+use tetra::math::Vec2;
+use tetra::Context;
+
+/// Width of the squares of the checkered band, in pixels.
+const SQUARE: f32 = 6.0;
+
+/// The end of a level: a checkered band from the top of the screen down to the ground.
+pub struct FinishLine {
+    mesh: Mesh,
+}
+
+impl FinishLine {
+    pub fn new(ctx: &mut Context, ground_y: f32) -> tetra::Result<FinishLine> {
+        let mut builder = GeometryBuilder::new();
+        for row in 0..(ground_y / SQUARE).ceil() as usize {
+            for column in 0..2 {
+                let color = if (row + column) % 2 == 0 {
+                    Color::WHITE
+                } else {
+                    Color::BLACK
+                };
+                builder.set_color(color);
+                builder.rectangle(
+                    ShapeStyle::Fill,
+                    Rectangle::new(
+                        column as f32 * SQUARE,
+                        row as f32 * SQUARE,
+                        SQUARE,
+                        SQUARE.min(ground_y - row as f32 * SQUARE),
+                    ),
+                )?;
+            }
+        }
+
+        Ok(FinishLine {
+            mesh: builder.build_mesh(ctx)?,
+        })
+    }
+
+    /// Draws the band with its left edge at `x`.
+    pub fn draw(&self, ctx: &mut Context, x: f32) {
+        self.mesh.draw(ctx, Vec2::new(x, 0.0));
+    }
+}
@@ -2,43 +2,53 @@ use tetra::graphics::{Rectangle, Texture};
 use tetra::math::Vec2;
 use tetra::Context;
 
-use crate::systems::physics::{check_collision, PhysicsBody};
-use crate::{SCREEN_WIDTH, SCROLL_SPEED};
+use crate::systems::physics::PhysicsBody;
+
+/// Width of `ground.png`, after which the scrolling wraps around.
+const TILE_WIDTH: f32 = 335.0;
+const HEIGHT: f32 = 112.0;
 
 pub struct Ground {
-    texture: Texture,
+    y: f32,
+    width: f32,
     scroll_pos: f32,
+    previous_scroll_pos: f32,
     pub scroll: bool,
 }
 
 impl PhysicsBody for Ground {
     fn get_collision_rect(&mut self) -> Rectangle {
-        Rectangle::new(0.0, 400.0, SCREEN_WIDTH as f32, 112.0)
-    }
-
-    fn collides_with(&mut self, obj: &Rectangle) -> bool {
-        check_collision(&self.get_collision_rect(), obj)
+        Rectangle::new(0.0, self.y, self.width, HEIGHT)
     }
 }
 
 impl Ground {
-    pub fn new(ctx: &mut Context) -> tetra::Result<Ground> {
-        Ok(Ground {
-            texture: Texture::new(ctx, "./resources/ground.png")?,
+    /// Creates ground whose top edge is at `y`, spanning `width` pixels.
+    pub fn new(y: f32, width: f32) -> Ground {
+        Ground {
+            y,
+            width,
             scroll_pos: 0.0,
+            previous_scroll_pos: 0.0,
             scroll: true,
-        })
+        }
     }
 
-    pub fn update(&mut self) {
+    pub fn update(&mut self, scroll_speed: f32, dt: f32) {
+        self.previous_scroll_pos = self.scroll_pos;
         if self.scroll {
-            self.scroll_pos = (self.scroll_pos - SCROLL_SPEED) % 335.0;
+            let unwrapped = self.scroll_pos - scroll_speed * dt;
+            self.scroll_pos = unwrapped % TILE_WIDTH;
+            // Keep interpolation continuous when the texture wraps around.
+            self.previous_scroll_pos += self.scroll_pos - unwrapped;
         }
     }
 
-    pub fn draw(&mut self, ctx: &mut Context) {
-        self.texture.draw(ctx, Vec2::new(self.scroll_pos, 400.0));
-        self.texture
-            .draw(ctx, Vec2::new(self.scroll_pos + 335.0, 400.0));
+    pub fn draw(&self, ctx: &mut Context, texture: &Texture, blend: f32) {
+        let mut x = self.previous_scroll_pos + (self.scroll_pos - self.previous_scroll_pos) * blend;
+        while x < self.width {
+            texture.draw(ctx, Vec2::new(x, self.y));
+            x += TILE_WIDTH;
+        }
     }
 }
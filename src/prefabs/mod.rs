@@ -1,6 +1,9 @@
 pub mod background;
 pub mod bird;
 pub mod button;
+pub mod debugoverlay;
+pub mod finishline;
 pub mod ground;
+pub mod notice;
 pub mod pipes;
 pub mod scoreboard;
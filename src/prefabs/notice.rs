@@ -0,0 +1,58 @@
+use tetra::graphics::text::Text;
+use tetra::graphics::{Color, DrawParams};
+use tetra::math::Vec2;
+use tetra::window;
+use tetra::Context;
+
+use crate::systems::assets;
+
+/// How long a notice stays on screen, in seconds.
+const DURATION: f32 = 3.0;
+/// How long a notice takes to fade out at the end, in seconds.
+const FADE_DURATION: f32 = 0.5;
+
+/// A short message shown at the bottom of the screen that fades out by itself.
+pub struct Notice {
+    text: Text,
+    remaining: f32,
+}
+
+impl Notice {
+    pub fn new(ctx: &mut Context) -> tetra::Result<Notice> {
+        Ok(Notice {
+            text: Text::new(
+                "",
+                assets::font(ctx, "./resources/font/flappy-font.ttf", 12.0)?,
+            ),
+            remaining: 0.0,
+        })
+    }
+
+    pub fn show(&mut self, message: &str) {
+        self.text.set_content(message);
+        self.remaining = DURATION;
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.remaining = (self.remaining - dt).max(0.0);
+    }
+
+    pub fn draw(&mut self, ctx: &mut Context) {
+        if self.remaining <= 0.0 {
+            return;
+        }
+
+        let bounds = self.text.get_bounds(ctx).unwrap_or_default();
+        let alpha = (self.remaining / FADE_DURATION).min(1.0);
+        self.text.draw(
+            ctx,
+            DrawParams::new()
+                .position(Vec2::new(
+                    window::get_width(ctx) as f32 / 2.0,
+                    window::get_height(ctx) as f32 - 8.0,
+                ))
+                .origin(Vec2::new(bounds.width / 2.0, bounds.height))
+                .color(Color::rgba(1.0, 1.0, 1.0, alpha)),
+        );
+    }
+}
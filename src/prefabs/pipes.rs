@@ -1,45 +1,227 @@
-use tetra::graphics::{Rectangle, Texture};
+use std::convert::TryFrom;
+use std::f32::consts::TAU;
+use std::sync::Arc;
+
+use serde::Deserialize;
+use tetra::graphics::{DrawParams, Rectangle, Texture};
 use tetra::math::Vec2;
 use tetra::Context;
 
-use crate::systems::physics::{check_collision, PhysicsBody};
-use crate::SCROLL_SPEED;
+use crate::systems::physics::{CollisionMask, PhysicsBody, PlacedMask, Shape};
+
+/// Height of the middle of the gap for an offset of zero.
+pub const GAP_CENTER: f32 = 220.0;
+
+/// The size and spacing of a pipe group. Drawing and collision are both derived from it,
+/// so they can't drift apart.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PipeGeometry {
+    /// Size of `pipes.png`, which holds the top and bottom pipe side by side.
+    pub texture_size: Vec2<f32>,
+    /// Width the pipes are drawn and collide at.
+    pub pipe_width: f32,
+    /// Vertical space between the top and the bottom pipe.
+    pub gap_height: f32,
+}
+
+impl PipeGeometry {
+    pub fn new(texture_size: Vec2<f32>, pipe_width: f32, gap_height: f32) -> PipeGeometry {
+        PipeGeometry {
+            texture_size,
+            pipe_width,
+            gap_height,
+        }
+    }
+
+    fn frame_size(&self) -> Vec2<f32> {
+        Vec2::new(self.texture_size.x / 2.0, self.texture_size.y)
+    }
+
+    /// How much the frames are stretched horizontally to get the pipe width.
+    fn scale(&self) -> Vec2<f32> {
+        Vec2::new(self.pipe_width / self.frame_size().x, 1.0)
+    }
+
+    pub fn pipe_height(&self) -> f32 {
+        self.frame_size().y
+    }
+
+    /// The height of a group's whole column, from the top of one pipe to the bottom of the other.
+    pub fn height(&self) -> f32 {
+        self.pipe_height() * 2.0 + self.gap_height
+    }
+
+    /// The top pipe for an `index` of 0, the bottom one for 1, colliding with `mask`.
+    fn pipe(&self, index: usize, mask: Arc<CollisionMask>) -> Pipe {
+        let frame_size = self.frame_size();
+
+        Pipe {
+            offset: Vec2::new(0.0, index as f32 * (self.pipe_height() + self.gap_height)),
+            position: Vec2::zero(),
+            previous_position: Vec2::zero(),
+            source_rect: Rectangle::new(
+                index as f32 * frame_size.x,
+                0.0,
+                frame_size.x,
+                frame_size.y,
+            ),
+            scale: self.scale(),
+            mask,
+            size: Vec2::new(self.pipe_width, self.pipe_height()),
+        }
+    }
+}
+
+/// How a pipe group moves besides scrolling with the ground. Still by default.
+/// Configured as `[amplitude, period, gap_swing, speed]`.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(try_from = "Vec<f32>")]
+pub struct PipeMotion {
+    /// How far the gap swings up and down, in pixels.
+    pub amplitude: f32,
+    /// Seconds per full swing, and per opening and closing of the gap.
+    pub period: f32,
+    /// How much wider and narrower than its height the gap gets, in pixels.
+    pub gap_swing: f32,
+    /// Horizontal speed compared to the ground, e.g. 1.2 for pipes that overtake it.
+    pub speed: f32,
+}
+
+impl Default for PipeMotion {
+    fn default() -> PipeMotion {
+        PipeMotion {
+            amplitude: 0.0,
+            period: 1.0,
+            gap_swing: 0.0,
+            speed: 1.0,
+        }
+    }
+}
+
+impl TryFrom<Vec<f32>> for PipeMotion {
+    type Error = String;
+
+    fn try_from(numbers: Vec<f32>) -> Result<PipeMotion, String> {
+        match *numbers.as_slice() {
+            [amplitude, period, gap_swing, speed] => {
+                PipeMotion::new(amplitude, period, gap_swing, speed)
+            }
+            _ => Err(format!(
+                "{:?} should be [amplitude, period, gap_swing, speed]",
+                numbers
+            )),
+        }
+    }
+}
+
+impl PipeMotion {
+    pub fn new(
+        amplitude: f32,
+        period: f32,
+        gap_swing: f32,
+        speed: f32,
+    ) -> Result<PipeMotion, String> {
+        let valid = amplitude >= 0.0 && gap_swing >= 0.0 && period > 0.0 && speed > 0.0;
+        if !valid || !(amplitude + period + gap_swing + speed).is_finite() {
+            return Err(
+                "pipe motion needs a positive period and speed, and no negative swing".to_string(),
+            );
+        }
+
+        Ok(PipeMotion {
+            amplitude,
+            period,
+            gap_swing,
+            speed,
+        })
+    }
+
+    /// How far the gap can end up from where the bird expects it, in pixels: the swing of
+    /// two groups in opposite directions, plus the gap closing on one side.
+    pub fn reach(&self) -> f32 {
+        self.amplitude * 2.0 + self.gap_swing / 2.0
+    }
+
+    /// How far the top and the bottom pipe are moved down `age` seconds after spawning.
+    fn shifts(&self, age: f32) -> (f32, f32) {
+        let wave = (age * TAU / self.period).sin();
+        let sway = self.amplitude * wave;
+        let opening = self.gap_swing / 2.0 * wave;
+
+        (sway - opening, sway + opening)
+    }
+}
 
 pub struct Pipe {
+    offset: Vec2<f32>,
     position: Vec2<f32>,
+    previous_position: Vec2<f32>,
     source_rect: Rectangle,
+    scale: Vec2<f32>,
+    mask: Arc<CollisionMask>,
+    size: Vec2<f32>,
 }
 
 impl Pipe {
-    fn new(position: Vec2<f32>, source_rect: Rectangle) -> Self {
-        Pipe {
+    /// Moves the pipe to its place in a group at `group_position`, `shift` pixels lower
+    /// than usual.
+    fn place(&mut self, group_position: Vec2<f32>, shift: f32) {
+        self.position = group_position + self.offset + Vec2::new(0.0, shift);
+    }
+
+    fn shapes_at(&self, position: Vec2<f32>) -> Vec<Shape> {
+        vec![Shape::Mask(PlacedMask {
+            mask: self.mask.clone(),
             position,
-            source_rect,
-        }
+            origin: Vec2::zero(),
+            rotation: 0.0,
+            scale: self.scale,
+        })]
+    }
+
+    /// Where the sprite lands on screen when the pipe is at `position`.
+    fn sprite_rect(&self, position: Vec2<f32>) -> Rectangle {
+        Rectangle::new(position.x, position.y, self.size.x, self.size.y)
     }
 
-    fn draw(&mut self, ctx: &mut Context, position: Vec2<f32>, texture: &Texture) {
+    /// How far the pipe moved during its group's last update.
+    pub fn motion(&self) -> Vec2<f32> {
+        self.position - self.previous_position
+    }
+
+    fn rewind(&mut self, t: f32) {
+        self.position = Vec2::lerp(self.previous_position, self.position, t);
+    }
+
+    fn draw(&mut self, ctx: &mut Context, texture: &Texture, blend: f32) {
         texture.draw_region(
             ctx,
             self.source_rect,
-            Vec2::new(self.position.x + position.x, self.position.y + position.y),
+            DrawParams::new()
+                .position(Vec2::lerp(self.previous_position, self.position, blend))
+                .scale(self.scale),
         );
     }
 }
 
 impl PhysicsBody for Pipe {
     fn get_collision_rect(&mut self) -> Rectangle {
-        Rectangle::new(self.position.x, self.position.y, 54.0, 320.0)
+        self.sprite_rect(self.position)
     }
 
-    fn collides_with(&mut self, obj: &Rectangle) -> bool {
-        check_collision(&self.get_collision_rect(), obj)
+    fn get_shapes(&mut self) -> Vec<Shape> {
+        self.shapes_at(self.position)
     }
 }
 
 pub struct PipeGroup {
     top_pipe: Pipe,
     bottom_pipe: Pipe,
+    masks: [Arc<CollisionMask>; 2],
+    geometry: PipeGeometry,
+    motion: PipeMotion,
+    /// Seconds since the group was spawned, while it was moving.
+    age: f32,
 
     pub position: Vec2<f32>,
     pub alive: bool,
@@ -48,38 +230,92 @@ pub struct PipeGroup {
 }
 
 impl PipeGroup {
-    pub fn new() -> tetra::Result<PipeGroup> {
-        Ok(PipeGroup {
+    /// Collides with `masks`, the top and the bottom pipe in `pipes.png`.
+    pub fn new(geometry: PipeGeometry, masks: [Arc<CollisionMask>; 2]) -> PipeGroup {
+        PipeGroup {
             position: Vec2::new(0.0, 0.0),
-            top_pipe: Pipe::new(Vec2::new(0.0, 0.0), Rectangle::new(0.0, 0.0, 54.0, 320.0)),
-            bottom_pipe: Pipe::new(
-                Vec2::new(0.0, 440.0),
-                Rectangle::new(54.0, 0.0, 54.0, 320.0),
-            ),
+            top_pipe: geometry.pipe(0, masks[0].clone()),
+            bottom_pipe: geometry.pipe(1, masks[1].clone()),
+            masks,
+            geometry,
+            motion: PipeMotion::default(),
+            age: 0.0,
             alive: false,
             enabled: false,
             has_scored: false,
-        })
+        }
     }
 
-    pub fn update(&mut self, _ctx: &mut Context) {
+    /// The middle of the gap between the pipes.
+    pub fn gap_center(&self) -> Vec2<f32> {
+        let top = self.top_pipe.position.y + self.geometry.pipe_height();
+        let bottom = self.bottom_pipe.position.y;
+        Vec2::new(
+            self.position.x + self.geometry.pipe_width / 2.0,
+            (top + bottom) / 2.0,
+        )
+    }
+
+    pub fn width(&self) -> f32 {
+        self.geometry.pipe_width
+    }
+
+    pub fn update(&mut self, scroll_speed: f32, dt: f32) {
+        self.top_pipe.previous_position = self.top_pipe.position;
+        self.bottom_pipe.previous_position = self.bottom_pipe.position;
         if self.alive && self.enabled {
-            self.position.x -= SCROLL_SPEED;
+            self.age += dt;
+            self.position.x -= scroll_speed * self.motion.speed * dt;
         }
-        if self.position.x < -54.0 {
+        self.place_pipes();
+        if self.position.x < -self.geometry.pipe_width {
             self.alive = false;
             self.enabled = false;
         }
     }
 
-    pub fn draw(&mut self, ctx: &mut Context, texture: &Texture) {
-        self.top_pipe.draw(ctx, self.position, texture);
-        self.bottom_pipe.draw(ctx, self.position, texture);
+    fn place_pipes(&mut self) {
+        let (top_shift, bottom_shift) = self.motion.shifts(self.age);
+        self.top_pipe.place(self.position, top_shift);
+        self.bottom_pipe.place(self.position, bottom_shift);
     }
 
-    pub fn reset(&mut self, x: f32, y: f32) {
+    pub fn draw(&mut self, ctx: &mut Context, texture: &Texture, blend: f32) {
+        self.top_pipe.draw(ctx, texture, blend);
+        self.bottom_pipe.draw(ctx, texture, blend);
+    }
+
+    /// Rebuilds the pipes for `geometry`, e.g. to narrow the gap as the game gets harder.
+    pub fn set_geometry(&mut self, geometry: PipeGeometry) {
+        if geometry != self.geometry {
+            self.top_pipe = geometry.pipe(0, self.masks[0].clone());
+            self.bottom_pipe = geometry.pipe(1, self.masks[1].clone());
+            self.geometry = geometry;
+        }
+    }
+
+    pub fn pipes_mut(&mut self) -> [&mut Pipe; 2] {
+        [&mut self.top_pipe, &mut self.bottom_pipe]
+    }
+
+    /// Moves the pipes back to `t` of the way through their last update, e.g. to a point
+    /// of impact.
+    pub fn rewind(&mut self, t: f32) {
+        self.top_pipe.rewind(t);
+        self.bottom_pipe.rewind(t);
+        self.position.x = self.top_pipe.position.x;
+    }
+
+    /// Places the group at `x`, with the middle of the gap `y` pixels below its usual
+    /// height, moving by `motion` from then on.
+    pub fn reset(&mut self, x: f32, y: f32, motion: PipeMotion) {
         self.position.x = x;
-        self.position.y = y - 160.0;
+        self.position.y = GAP_CENTER + y - self.geometry.height() / 2.0;
+        self.motion = motion;
+        self.age = 0.0;
+        self.place_pipes();
+        self.top_pipe.previous_position = self.top_pipe.position;
+        self.bottom_pipe.previous_position = self.bottom_pipe.position;
         self.alive = true;
         self.enabled = true;
         self.has_scored = false;
@@ -87,33 +323,40 @@ impl PipeGroup {
 }
 
 impl PhysicsBody for PipeGroup {
+    /// Covers both pipes wherever their motion has taken them.
     fn get_collision_rect(&mut self) -> Rectangle {
-        Rectangle::new(0.0, 0.0, 0.0, 0.0)
+        let top = self.top_pipe.position.y;
+        let bottom = self.bottom_pipe.position.y + self.geometry.pipe_height();
+        Rectangle::new(self.position.x, top, self.geometry.pipe_width, bottom - top)
     }
 
-    fn collides_with(&mut self, obj: &Rectangle) -> bool {
-        let relative_rect = Rectangle::new(
-            obj.x - self.position.x - 27.0,
-            obj.y - self.position.y - 12.0,
-            obj.width,
-            obj.height,
-        );
-        self.top_pipe.collides_with(&relative_rect)
-            || self.bottom_pipe.collides_with(&relative_rect)
+    fn get_shapes(&mut self) -> Vec<Shape> {
+        let mut shapes = self.top_pipe.get_shapes();
+        shapes.extend(self.bottom_pipe.get_shapes());
+        shapes
     }
 }
 
-pub struct PipeGenerator {
-    counter: i32,
+/// Decides when the next pipe group is due. Where its gap goes is up to the
+/// `PipeGenerator` of the game mode.
+pub struct PipeSpawner {
+    elapsed: f32,
     enabled: bool,
+    spawn_interval: f32,
 }
 
-impl PipeGenerator {
-    pub fn new() -> tetra::Result<PipeGenerator> {
-        Ok(PipeGenerator {
-            counter: 0,
+impl PipeSpawner {
+    /// Spawns a group every `spawn_interval` seconds once started.
+    pub fn new(spawn_interval: f32) -> PipeSpawner {
+        PipeSpawner {
+            elapsed: 0.0,
             enabled: false,
-        })
+            spawn_interval,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = PipeSpawner::new(self.spawn_interval);
     }
 
     pub fn start(&mut self) {
@@ -124,15 +367,172 @@ impl PipeGenerator {
         self.enabled = false;
     }
 
-    pub fn should_spawn_pipe(&mut self) -> bool {
+    pub fn should_spawn_pipe(&mut self, dt: f32) -> bool {
         if self.enabled {
-            self.counter += 1;
-            if self.counter >= 80 {
-                self.counter = 0;
+            self.elapsed += dt;
+            if self.elapsed >= self.spawn_interval {
+                self.elapsed -= self.spawn_interval;
                 return true;
             }
         }
 
         false
     }
+
+    /// Time since the last pipe group was spawned, in seconds.
+    pub fn elapsed(&self) -> f32 {
+        self.elapsed
+    }
+
+    pub fn spawn_interval(&self) -> f32 {
+        self.spawn_interval
+    }
+
+    pub fn set_spawn_interval(&mut self, spawn_interval: f32) {
+        self.spawn_interval = spawn_interval;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::prefabs::bird::{Bird, Hitbox, Pose, BIRD_ORIGIN};
+    use crate::systems::physics::{self, check_rect_collision};
+    use crate::systems::sprites::Sprites;
+    use crate::systems::tuning::Tuning;
+
+    /// Whether `outer` covers `inner`, give or take rounding.
+    fn contains(outer: &Rectangle, inner: &Rectangle) -> bool {
+        const SLACK: f32 = 0.001;
+        inner.x >= outer.x - SLACK
+            && inner.y >= outer.y - SLACK
+            && inner.x + inner.width <= outer.x + outer.width + SLACK
+            && inner.y + inner.height <= outer.y + outer.height + SLACK
+    }
+
+    /// Every whole-pixel gap offset the default tuning can pick.
+    fn offsets(tuning: &Tuning) -> impl Iterator<Item = f32> {
+        let range = tuning.gap_offset_range as i32;
+        (-range..=range).map(|offset| offset as f32)
+    }
+
+    fn pipe_group(tuning: &Tuning, sprites: &Sprites) -> PipeGroup {
+        PipeGroup::new(
+            PipeGeometry::new(sprites.pipes_size, tuning.pipe_width, tuning.pipe_gap),
+            sprites.pipe_masks.clone(),
+        )
+    }
+
+    /// Where the bird's current frame lands on screen at `position`, unrotated.
+    fn bird_sprite_rect(position: Vec2<f32>) -> Rectangle {
+        let frame = Bird::frames()[0];
+        Rectangle::new(
+            position.x - BIRD_ORIGIN.x,
+            position.y - BIRD_ORIGIN.y,
+            frame.width,
+            frame.height,
+        )
+    }
+
+    #[test]
+    fn collision_rect_bounds_both_pipes_at_every_offset() {
+        let tuning = Tuning::default();
+        let sprites = Sprites::solid();
+        let mut group = pipe_group(&tuning, &sprites);
+
+        for offset in offsets(&tuning) {
+            group.reset(200.0, offset, PipeMotion::default());
+            let bounds = group.get_collision_rect();
+
+            for pipe in group.pipes_mut() {
+                let sprite = pipe.sprite_rect(pipe.position);
+                assert!(contains(&bounds, &sprite), "offset {}", offset);
+                for shape in pipe.get_shapes() {
+                    assert!(contains(&sprite, &shape.bounds()), "offset {}", offset);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn collision_rect_follows_moving_pipes() {
+        let tuning = Tuning::default();
+        let sprites = Sprites::solid();
+        let mut group = pipe_group(&tuning, &sprites);
+        let motion = PipeMotion::new(30.0, 2.0, 40.0, 1.0).unwrap();
+
+        group.reset(200.0, 0.0, motion);
+        for _ in 0..240 {
+            group.update(60.0, 1.0 / 60.0);
+            let bounds = group.get_collision_rect();
+            for pipe in group.pipes_mut() {
+                assert!(
+                    contains(&bounds, &pipe.sprite_rect(pipe.position)),
+                    "{:?} {:?}",
+                    bounds,
+                    pipe.sprite_rect(pipe.position)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn bird_shapes_stay_inside_its_sprite() {
+        let sprites = Sprites::solid();
+        let pose = Pose {
+            position: Vec2::new(100.0, 200.0),
+            rotation: 0.0,
+        };
+
+        for hitbox in [Hitbox::Mask, Hitbox::Circle, Hitbox::Capsule, Hitbox::Box] {
+            let mut bird = Bird::new(&Tuning::default(), sprites.bird_masks.clone());
+            bird.hitbox = hitbox;
+            for shape in bird.shapes_at(pose) {
+                assert!(
+                    contains(&bird_sprite_rect(pose.position), &shape.bounds()),
+                    "{:?}",
+                    hitbox
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn bird_collides_where_the_drawn_sprites_overlap_at_every_offset() {
+        // With every pixel solid, the sprites overlap exactly where the masks do.
+        let tuning = Tuning::default();
+        let sprites = Sprites::solid();
+        let bird = Bird::new(&tuning, sprites.bird_masks.clone());
+        let mut group = pipe_group(&tuning, &sprites);
+
+        for offset in offsets(&tuning) {
+            group.reset(100.0, offset, PipeMotion::default());
+            let pipe_sprites: Vec<Rectangle> = group
+                .pipes_mut()
+                .iter()
+                .map(|pipe| pipe.sprite_rect(pipe.position))
+                .collect();
+            let pipe_shapes = group.get_shapes();
+            let gap = group.gap_center();
+
+            let reach = tuning.pipe_gap as i32 / 2 + 20;
+            for x in [60, 80, 100, 170, 190] {
+                for dy in -reach..=reach {
+                    let position = Vec2::new(x as f32, gap.y + dy as f32);
+                    let sprite = bird_sprite_rect(position);
+                    let overlaps = pipe_sprites
+                        .iter()
+                        .any(|pipe| check_rect_collision(&sprite, pipe));
+                    let pose = Pose {
+                        position,
+                        rotation: 0.0,
+                    };
+                    let collides = physics::check_collision(&bird.shapes_at(pose), &pipe_shapes);
+
+                    assert_eq!(collides, overlaps, "offset {} at {:?}", offset, position);
+                }
+            }
+        }
+    }
 }
@@ -1,10 +1,11 @@
-use tetra::graphics::text::{Font, Text};
+use tetra::graphics::text::Text;
 use tetra::graphics::{DrawParams, Rectangle, Texture};
 use tetra::math::Vec2;
+use tetra::window;
 use tetra::Context;
 
 use crate::prefabs::button::Button;
-use crate::SCREEN_WIDTH;
+use crate::systems::assets;
 
 pub struct Scoreboard {
     game_over_texture: Texture,
@@ -22,51 +23,64 @@ pub struct Scoreboard {
     highscore_text: Text,
     highscore_origin: Vec2<f32>,
 
+    caption_text: Text,
+    caption_origin: Vec2<f32>,
+
     medal: Texture,
+    /// Whether a level was finished, rather than the run ending in a crash.
+    cleared: bool,
 
     pub button: Button,
 }
 
 impl Scoreboard {
     pub fn new(ctx: &mut Context) -> tetra::Result<Scoreboard> {
-        let game_over_texture = Texture::new(ctx, "./resources/gameover.png")?;
-        let scoreboard_texture = Texture::new(ctx, "./resources/scoreboard.png")?;
+        let game_over_texture = assets::texture(ctx, "./resources/gameover.png")?;
+        let scoreboard_texture = assets::texture(ctx, "./resources/scoreboard.png")?;
 
         Ok(Scoreboard {
-            game_over_position: Vec2::new(SCREEN_WIDTH as f32 / 2.0, 100.0),
+            game_over_position: Vec2::new(window::get_width(ctx) as f32 / 2.0, 100.0),
             game_over_origin: Vec2::new(
                 game_over_texture.width() as f32 / 2.0,
                 game_over_texture.height() as f32 / 2.0,
             ),
             game_over_texture,
 
-            scoreboard_position: Vec2::new(SCREEN_WIDTH as f32 / 2.0, 200.0),
+            scoreboard_position: Vec2::new(window::get_width(ctx) as f32 / 2.0, 200.0),
             scoreboard_origin: Vec2::new(
                 scoreboard_texture.width() as f32 / 2.0,
                 scoreboard_texture.height() as f32 / 2.0,
             ),
             scoreboard_texture,
 
-            button: Button::new(ctx, Vec2::new(SCREEN_WIDTH as f32 / 2.0, 300.0))?,
+            button: Button::new(ctx, Vec2::new(window::get_width(ctx) as f32 / 2.0, 300.0))?,
 
             score_text: Text::new(
                 "0",
-                Font::vector(ctx, "./resources/font/flappy-font.ttf", 26.0)?,
+                assets::font(ctx, "./resources/font/flappy-font.ttf", 26.0)?,
             ),
             score_origin: Vec2::new(0.0, 0.0),
             highscore_text: Text::new(
                 "0",
-                Font::vector(ctx, "./resources/font/flappy-font.ttf", 26.0)?,
+                assets::font(ctx, "./resources/font/flappy-font.ttf", 26.0)?,
             ),
 
             highscore_origin: Vec2::new(0.0, 0.0),
             score: 0,
 
-            medal: Texture::new(ctx, "./resources/medals.png")?,
+            caption_text: Text::new(
+                "",
+                assets::font(ctx, "./resources/font/flappy-font.ttf", 14.0)?,
+            ),
+            caption_origin: Vec2::new(0.0, 0.0),
+
+            medal: assets::texture(ctx, "./resources/medals.png")?,
+            cleared: false,
         })
     }
 
-    pub fn set_score(&mut self, ctx: &mut Context, score: i32, highscore: i32) {
+    /// Shows `score` and `highscore`, with `caption` below the board, e.g. the seed.
+    pub fn set_score(&mut self, ctx: &mut Context, score: i32, highscore: i32, caption: &str) {
         self.score = score;
 
         self.score_text.set_content(score.to_string());
@@ -76,15 +90,26 @@ impl Scoreboard {
         self.highscore_text.set_content(highscore.to_string());
         let bounds = self.highscore_text.get_bounds(ctx).unwrap();
         self.highscore_origin = Vec2::new(bounds.width, 0.0);
+
+        self.caption_text.set_content(caption);
+        let bounds = self.caption_text.get_bounds(ctx).unwrap();
+        self.caption_origin = Vec2::new(bounds.width / 2.0, 0.0);
+    }
+
+    /// Leaves out the game over banner, for a finished level.
+    pub fn set_cleared(&mut self, cleared: bool) {
+        self.cleared = cleared;
     }
 
     pub fn draw(&mut self, ctx: &mut Context) {
-        self.game_over_texture.draw(
-            ctx,
-            DrawParams::new()
-                .position(self.game_over_position)
-                .origin(self.game_over_origin),
-        );
+        if !self.cleared {
+            self.game_over_texture.draw(
+                ctx,
+                DrawParams::new()
+                    .position(self.game_over_position)
+                    .origin(self.game_over_origin),
+            );
+        }
         self.scoreboard_texture.draw(
             ctx,
             DrawParams::new()
@@ -108,6 +133,13 @@ impl Scoreboard {
                 .origin(self.highscore_origin),
         );
 
+        self.caption_text.draw(
+            ctx,
+            DrawParams::new()
+                .position(Vec2::new(window::get_width(ctx) as f32 / 2.0, 340.0))
+                .origin(self.caption_origin),
+        );
+
         if self.score >= 10 && self.score < 20 {
             self.medal.draw_region(
                 ctx,
@@ -0,0 +1,416 @@
+use std::sync::Arc;
+
+use tetra::graphics::mesh::{GeometryBuilder, ShapeStyle};
+use tetra::graphics::text::Text;
+use tetra::graphics::{Color, DrawParams, Rectangle, Texture};
+use tetra::input::{self, Key, MouseButton};
+use tetra::math::Vec2;
+use tetra::time;
+use tetra::window;
+use tetra::Context;
+
+use crate::prefabs::background::Background;
+use crate::prefabs::bird::BIRD_X;
+use crate::prefabs::finishline::FinishLine;
+use crate::prefabs::ground::Ground;
+use crate::prefabs::notice::Notice;
+use crate::prefabs::pipes::{PipeGeometry, PipeGroup, PipeMotion, GAP_CENTER};
+use crate::scenes::{game::GameScene, Scene, Transition};
+use crate::systems::assets;
+use crate::systems::config::Config;
+use crate::systems::level::{Level, LevelPipe};
+use crate::systems::physics::{CollisionMask, PhysicsBody};
+use crate::systems::pipegenerators::Gap;
+use crate::systems::transitioneffects::Effect;
+
+/// How fast the arrow keys scroll through the level, in pixels per second.
+const SCROLL_SPEED: f32 = 400.0;
+/// How far one notch of the mouse wheel scrolls, in pixels.
+const WHEEL_STEP: f32 = 40.0;
+/// How much the up and down keys change the height of a gap, in pixels.
+const HEIGHT_STEP: f32 = 5.0;
+const MIN_GAP_HEIGHT: f32 = 50.0;
+const MAX_GAP_HEIGHT: f32 = 300.0;
+const SELECTION_COLOR: Color = Color::rgb(1.0, 1.0, 0.0);
+const START_COLOR: Color = Color::rgba(1.0, 1.0, 1.0, 0.5);
+
+/// A pipe group being edited, at `x` pixels into the level.
+#[derive(Clone, Copy)]
+struct Placed {
+    x: f32,
+    gap: Gap,
+}
+
+/// The pipe group being dragged, and where it was grabbed relative to the middle of
+/// its gap.
+struct Drag {
+    index: usize,
+    grab: Vec2<f32>,
+}
+
+/// Lays out a level by hand. Pipe groups are placed and picked up with the left mouse
+/// button and removed with the right one, while the arrow keys or the mouse wheel
+/// scroll through the level. The scene works in level pixels, where a group at `x`
+/// enters the screen once `x` minus the screen width has scrolled by.
+pub struct EditorScene {
+    sky_texture: Texture,
+    background: Background,
+    ground: Ground,
+    ground_texture: Texture,
+    pipes_texture: Texture,
+    finish_line: FinishLine,
+    status: Text,
+    help: Text,
+    notice: Notice,
+
+    level: Level,
+    pipes: Vec<Placed>,
+    geometry: PipeGeometry,
+    pipe_masks: [Arc<CollisionMask>; 2],
+    /// The level pixel at the left edge of the screen.
+    camera: f32,
+    selected: Option<usize>,
+    drag: Option<Drag>,
+    /// Whether there are changes that haven't been saved.
+    dirty: bool,
+    /// Set once Escape was pressed with unsaved changes, so a second press leaves.
+    leaving: bool,
+
+    config: Config,
+}
+
+impl EditorScene {
+    pub fn new(ctx: &mut Context, config: Config, level: Level) -> tetra::Result<EditorScene> {
+        let width = window::get_width(ctx) as f32;
+        let mut x = width;
+        let pipes = level
+            .pipes
+            .iter()
+            .map(|pipe| {
+                x += pipe.distance;
+                Placed { x, gap: pipe.gap }
+            })
+            .collect();
+        let font = assets::font(ctx, "./resources/font/flappy-font.ttf", 10.0)?;
+        let pipes_texture = assets::texture(ctx, "./resources/pipes.png")?;
+        let geometry = PipeGeometry::new(
+            Vec2::new(pipes_texture.width() as f32, pipes_texture.height() as f32),
+            config.tuning.pipe_width,
+            config.tuning.pipe_gap,
+        );
+        let pipe_masks = assets::sprites()?.pipe_masks;
+
+        Ok(EditorScene {
+            sky_texture: assets::texture(ctx, "./resources/sky.png")?,
+            background: Background::new(ctx)?,
+            ground: Ground::new(config.tuning.ground_y, width),
+            ground_texture: assets::texture(ctx, "./resources/ground.png")?,
+            pipes_texture,
+            finish_line: FinishLine::new(ctx, config.tuning.ground_y)?,
+            status: Text::new("", font.clone()),
+            help: Text::new(
+                "CLICK PLACE/MOVE  RIGHT CLICK DELETE\nUP/DOWN GAP  P PLAY-TEST  S SAVE",
+                font,
+            ),
+            notice: Notice::new(ctx)?,
+
+            level,
+            pipes,
+            geometry,
+            pipe_masks,
+            camera: 0.0,
+            selected: None,
+            drag: None,
+            dirty: false,
+            leaving: false,
+
+            config,
+        })
+    }
+
+    /// The level as edited, with its pipe groups in order.
+    fn to_level(&self, screen_width: f32) -> Level {
+        let mut pipes = self.pipes.clone();
+        pipes.sort_by(|a, b| a.x.total_cmp(&b.x));
+
+        let mut previous = screen_width;
+        let pipes = pipes
+            .iter()
+            .map(|placed| {
+                // Groups in the same place would need a distance of zero.
+                let distance = (placed.x - previous).max(1.0);
+                previous += distance;
+                LevelPipe {
+                    distance,
+                    gap: placed.gap,
+                }
+            })
+            .collect();
+
+        Level {
+            pipes,
+            ..self.level.clone()
+        }
+    }
+
+    fn gap_height(&self, gap: &Gap) -> f32 {
+        gap.height.unwrap_or(self.config.tuning.pipe_gap)
+    }
+
+    /// The pipe group of `placed`, drawn where the camera puts it.
+    fn pipe_group(&self, placed: &Placed) -> PipeGroup {
+        let mut pipe_group = PipeGroup::new(
+            PipeGeometry {
+                gap_height: self.gap_height(&placed.gap),
+                ..self.geometry
+            },
+            self.pipe_masks.clone(),
+        );
+        pipe_group.reset(
+            placed.x - self.camera,
+            placed.gap.offset,
+            PipeMotion::default(),
+        );
+        pipe_group
+    }
+
+    /// The group whose column contains the level pixel `x`.
+    fn pipe_at(&self, x: f32) -> Option<usize> {
+        self.pipes
+            .iter()
+            .rposition(|placed| x >= placed.x && x <= placed.x + self.geometry.pipe_width)
+    }
+
+    /// Moves the group at `index` so the point grabbed by `grab` ends up at `point`, in
+    /// level pixels. Groups stay right of the start, with their gap above the ground.
+    fn move_pipe(&mut self, index: usize, point: Vec2<f32>, grab: Vec2<f32>, screen_width: f32) {
+        let height = self.gap_height(&self.pipes[index].gap);
+        let lowest = self.config.tuning.ground_y - GAP_CENTER - height / 2.0;
+        let highest = height / 2.0 - GAP_CENTER;
+
+        let placed = &mut self.pipes[index];
+        placed.x = (point.x - grab.x).round().max(screen_width + 1.0);
+        placed.gap.offset = (point.y - grab.y - GAP_CENTER)
+            .round()
+            .clamp(highest, lowest.max(highest));
+        self.changed();
+    }
+
+    fn changed(&mut self) {
+        self.dirty = true;
+        self.leaving = false;
+    }
+
+    fn remove_pipe(&mut self, index: usize) {
+        self.pipes.remove(index);
+        self.selected = None;
+        self.drag = None;
+        self.changed();
+    }
+
+    fn resize_gap(&mut self, change: f32) {
+        if let Some(index) = self.selected {
+            let height = self.gap_height(&self.pipes[index].gap);
+            self.pipes[index].gap.height =
+                Some((height + change).clamp(MIN_GAP_HEIGHT, MAX_GAP_HEIGHT));
+            self.changed();
+        }
+    }
+
+    fn save(&mut self, screen_width: f32) {
+        let level = self.to_level(screen_width);
+        if level.pipes.is_empty() {
+            self.notice.show("Place a pipe before saving");
+            return;
+        }
+
+        match level.save() {
+            Ok(()) => {
+                self.dirty = false;
+                self.notice
+                    .show(&format!("Saved {}", level.path().display()));
+            }
+            Err(err) => self.notice.show(&format!("Couldn't save: {}", err)),
+        }
+    }
+}
+
+impl Scene for EditorScene {
+    fn update(&mut self, ctx: &mut Context) -> tetra::Result<Transition> {
+        let dt = time::get_delta_time(ctx).as_secs_f32();
+        let screen_width = window::get_width(ctx) as f32;
+        self.notice.update(dt);
+
+        if input::is_key_down(ctx, Key::Left) {
+            self.camera -= SCROLL_SPEED * dt;
+        }
+        if input::is_key_down(ctx, Key::Right) {
+            self.camera += SCROLL_SPEED * dt;
+        }
+        self.camera -= input::get_mouse_wheel_movement(ctx).y as f32 * WHEEL_STEP;
+        self.camera = self.camera.max(0.0);
+
+        let mouse = input::get_mouse_position(ctx);
+        let point = Vec2::new(mouse.x + self.camera, mouse.y);
+
+        if input::is_mouse_button_pressed(ctx, MouseButton::Left) {
+            let index = match self.pipe_at(point.x) {
+                Some(index) => index,
+                None => {
+                    self.pipes.push(Placed {
+                        x: point.x - self.geometry.pipe_width / 2.0,
+                        gap: Gap {
+                            offset: 0.0,
+                            height: None,
+                            motion: None,
+                        },
+                    });
+                    self.pipes.len() - 1
+                }
+            };
+            let placed = self.pipes[index];
+            let grab = point - Vec2::new(placed.x, GAP_CENTER + placed.gap.offset);
+            self.selected = Some(index);
+            self.drag = Some(Drag { index, grab });
+        }
+        if input::is_mouse_button_released(ctx, MouseButton::Left) {
+            self.drag = None;
+        }
+        if let Some(Drag { index, grab }) = self.drag {
+            self.move_pipe(index, point, grab, screen_width);
+        }
+
+        if input::is_mouse_button_pressed(ctx, MouseButton::Right) {
+            if let Some(index) = self.pipe_at(point.x) {
+                self.remove_pipe(index);
+            }
+        }
+        if input::is_key_pressed(ctx, Key::Delete) || input::is_key_pressed(ctx, Key::Backspace) {
+            if let Some(index) = self.selected {
+                self.remove_pipe(index);
+            }
+        }
+        if input::is_key_pressed(ctx, Key::Up) {
+            self.resize_gap(HEIGHT_STEP);
+        }
+        if input::is_key_pressed(ctx, Key::Down) {
+            self.resize_gap(-HEIGHT_STEP);
+        }
+
+        if input::is_key_pressed(ctx, Key::S) {
+            self.save(screen_width);
+        }
+
+        if input::is_key_pressed(ctx, Key::P) {
+            let level = self.to_level(screen_width);
+            if level.pipes.is_empty() {
+                self.notice.show("Place a pipe to play-test");
+            } else {
+                return Ok(Transition::Push(Box::new(GameScene::play_test(
+                    ctx,
+                    &self.config,
+                    level,
+                    self.camera,
+                )?))
+                .animated(Effect::Wipe, self.config.transition_duration));
+            }
+        }
+
+        if input::is_key_pressed(ctx, Key::Escape) {
+            if !self.dirty || self.leaving {
+                return Ok(
+                    Transition::Pop.animated(Effect::FadeToBlack, self.config.transition_duration)
+                );
+            }
+            self.leaving = true;
+            self.notice
+                .show("Unsaved changes, press Escape again to leave");
+        }
+
+        Ok(Transition::None)
+    }
+
+    /// The mouse button may be let go while play-testing.
+    fn on_pause(&mut self, _ctx: &mut Context) -> tetra::Result {
+        self.drag = None;
+        Ok(())
+    }
+
+    fn draw(&mut self, ctx: &mut Context) -> tetra::Result {
+        let screen_width = window::get_width(ctx) as f32;
+        self.sky_texture.draw(ctx, Vec2::zero());
+        self.background.draw(ctx);
+
+        let mut lines = GeometryBuilder::new();
+        lines.set_color(START_COLOR);
+        let start_x = screen_width - self.camera;
+        // Pipe groups can't go left of the start, nor can the bird fly there.
+        lines.polyline(
+            1.0,
+            &[
+                Vec2::new(start_x, 0.0),
+                Vec2::new(start_x, self.config.tuning.ground_y),
+            ],
+        )?;
+
+        for (index, placed) in self.pipes.iter().enumerate() {
+            let mut pipe_group = self.pipe_group(placed);
+            pipe_group.draw(ctx, &self.pipes_texture, 1.0);
+
+            if self.selected == Some(index) {
+                lines.set_color(SELECTION_COLOR);
+                lines.rectangle(ShapeStyle::Stroke(2.0), pipe_group.get_collision_rect())?;
+            }
+        }
+        lines.build_mesh(ctx)?.draw(ctx, Vec2::zero());
+
+        let last_x = self
+            .pipes
+            .iter()
+            .map(|placed| placed.x)
+            .fold(None, |last, x| {
+                Some(last.map_or(x, |last: f32| last.max(x)))
+            });
+        if let Some(last_x) = last_x {
+            self.finish_line
+                .draw(ctx, last_x + self.level.finish - self.camera);
+        }
+
+        self.ground.draw(ctx, &self.ground_texture, 1.0);
+
+        // Where the bird starts when play-testing from here.
+        let bird_y = self.config.tuning.screen_height as f32 / 2.0;
+        let marker = Rectangle::new(BIRD_X - 17.0, bird_y - 12.0, 34.0, 24.0);
+        GeometryBuilder::new()
+            .set_color(START_COLOR)
+            .rectangle(ShapeStyle::Stroke(1.0), marker)
+            .and_then(|builder| builder.build_mesh(ctx))?
+            .draw(ctx, Vec2::zero());
+
+        let mut status = format!(
+            "{}  {:.0} PX{}",
+            self.level.name.to_uppercase(),
+            self.camera,
+            if self.dirty { "  *" } else { "" }
+        );
+        if let Some(index) = self.selected {
+            let gap = self.pipes[index].gap;
+            status += &format!(
+                "\nOFFSET {:.0}  GAP {:.0}",
+                gap.offset,
+                self.gap_height(&gap)
+            );
+        }
+        self.status.set_content(status);
+        self.status
+            .draw(ctx, DrawParams::new().position(Vec2::new(4.0, 4.0)));
+        self.help.draw(
+            ctx,
+            DrawParams::new().position(Vec2::new(4.0, self.config.tuning.ground_y + 8.0)),
+        );
+
+        self.notice.draw(ctx);
+
+        Ok(())
+    }
+}
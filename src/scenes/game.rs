@@ -1,227 +1,539 @@
+use std::cell::Cell;
+use std::rc::Rc;
+use std::time::Duration;
 use tetra::audio::Sound;
-use tetra::graphics::text::{Font, Text};
+use tetra::graphics::animation::Animation;
+use tetra::graphics::text::Text;
 use tetra::graphics::{DrawParams, Texture};
 use tetra::input::{self, Key, MouseButton};
 use tetra::math::Vec2;
+use tetra::time;
+use tetra::window;
 use tetra::Context;
 
-use rand::{thread_rng, Rng};
-
 use crate::prefabs::background::Background;
-use crate::prefabs::bird::Bird;
-use crate::prefabs::ground::Ground;
-use crate::prefabs::pipes::{PipeGenerator, PipeGroup};
+use crate::prefabs::bird::{Bird, GhostBird, Pose, FRAME_LENGTH};
+use crate::prefabs::debugoverlay::DebugOverlay;
+use crate::prefabs::finishline::FinishLine;
+use crate::prefabs::notice::Notice;
 use crate::prefabs::scoreboard::Scoreboard;
+use crate::scenes::pause::{PauseChoice, PauseScene};
 use crate::scenes::{Scene, Transition};
-use crate::systems::physics::PhysicsBody;
+use crate::systems::assets;
+use crate::systems::autopilot::Autopilot;
+use crate::systems::config::Config;
+use crate::systems::controls::{InputSource, MouseInput, ReplayInput};
+use crate::systems::hotreload::{self, ConfigWatcher};
+use crate::systems::level::Level;
+use crate::systems::replay::Replay;
+use crate::systems::simulation::{Event, Input, Simulation};
 use crate::systems::storage;
-use crate::SCREEN_WIDTH;
+use crate::systems::timestep::FixedTimestep;
+use crate::systems::transitioneffects::Effect;
+use crate::systems::tuning::Tuning;
+
+/// How long the demo waits after a crash before flying a new seed, in seconds.
+const DEMO_RESTART_DELAY: f32 = 2.0;
+/// How long the game counts down after a pause before carrying on, in seconds.
+const RESUME_COUNTDOWN: f32 = 3.0;
 
 pub struct GameScene {
     sky_texture: Texture,
     background: Background,
-    ground: Ground,
+    ground_texture: Texture,
     pipes_texture: Texture,
+    finish_line: FinishLine,
+    bird_animation: Animation,
+    ghost_animation: Animation,
 
     instructions: Texture,
     get_ready: Texture,
 
-    bird: Bird,
-
     flap_sound: Sound,
     ground_hit_sound: Sound,
     pipe_hit_sound: Sound,
     score_sound: Sound,
 
-    score: i32,
     highscore: i32,
     score_text: Text,
-
-    is_mouse_down: bool,
-    instructions_visible: bool,
-
-    pipes: Vec<PipeGroup>,
-    game_over: bool,
-    pipe_generator: PipeGenerator,
+    level: usize,
+    level_text: Text,
+
+    input_source: Box<dyn InputSource>,
+    recording: Replay,
+    watching: Option<Replay>,
+    /// Whether the tuning changed mid-run, so the recording can't be played back.
+    retuned: bool,
+    /// Whether a level is being play-tested from the editor.
+    testing: bool,
+    /// Whether the autopilot is flying, as a demo that a click takes over and any key
+    /// leaves.
+    demo: bool,
+    /// Seconds since the demo crashed.
+    demo_crashed_for: f32,
+    /// What was picked in the pause menu, once it closes.
+    pause_choice: Rc<Cell<PauseChoice>>,
+    /// Seconds left until the game carries on after a pause.
+    countdown: f32,
+    countdown_text: Text,
+    /// Set once "Quit" was picked in the pause menu.
+    quitting: bool,
+
+    simulation: Simulation,
+    timestep: FixedTimestep,
+    ghost: GhostBird,
+    trace: Vec<Pose>,
 
     scoreboard: Scoreboard,
+    debug_overlay: DebugOverlay,
+    notice: Notice,
+
+    config: Config,
+    config_watcher: ConfigWatcher,
 }
 
 impl GameScene {
-    pub fn new(ctx: &mut Context) -> tetra::Result<GameScene> {
-        let mut bird = Bird::new(ctx)?;
-        bird.reset();
+    pub fn new(ctx: &mut Context, config: &Config) -> tetra::Result<GameScene> {
+        GameScene::build(
+            ctx,
+            config,
+            GameScene::simulation(config.run_seed(), config)?,
+            Box::new(MouseInput::new()),
+            None,
+        )
+    }
 
-        Ok(GameScene {
-            sky_texture: Texture::new(ctx, "./resources/sky.png")?,
-            background: Background::new(ctx)?,
-            ground: Ground::new(ctx)?,
-            pipes_texture: Texture::new(ctx, "./resources/pipes.png")?,
-            get_ready: Texture::new(ctx, "./resources/get-ready.png")?,
-            instructions: Texture::new(ctx, "./resources/instructions.png")?,
+    /// Plays `level` of the campaign. Levels aren't recorded and don't count towards
+    /// the highscore.
+    pub fn level(ctx: &mut Context, config: &Config, level: Level) -> tetra::Result<GameScene> {
+        let mut simulation = GameScene::simulation(config.run_seed(), config)?;
+        simulation.play_level(level, 0.0);
+
+        GameScene::build(ctx, config, simulation, Box::new(MouseInput::new()), None)
+    }
 
-            bird,
+    /// Plays `level` from `start` pixels in, without saving the stars earned.
+    pub fn play_test(
+        ctx: &mut Context,
+        config: &Config,
+        level: Level,
+        start: f32,
+    ) -> tetra::Result<GameScene> {
+        let mut simulation = GameScene::simulation(config.run_seed(), config)?;
+        simulation.play_level(level, start);
+
+        let mut scene =
+            GameScene::build(ctx, config, simulation, Box::new(MouseInput::new()), None)?;
+        scene.testing = true;
+        Ok(scene)
+    }
 
-            flap_sound: Sound::new("./resources/flap.wav")?,
-            ground_hit_sound: Sound::new("./resources/ground-hit.wav")?,
-            pipe_hit_sound: Sound::new("./resources/pipe-hit.wav")?,
-            score_sound: Sound::new("./resources/score.wav")?,
+    /// Lets the autopilot fly one random seed after another.
+    pub fn demo(ctx: &mut Context, config: &Config) -> tetra::Result<GameScene> {
+        let mut scene = GameScene::build(
+            ctx,
+            config,
+            GameScene::simulation(config.run_seed(), config)?,
+            Box::new(Autopilot::new()),
+            None,
+        )?;
+        scene.demo = true;
+        scene.notice.show("DEMO - CLICK TO PLAY");
+        Ok(scene)
+    }
 
-            score: 0,
-            highscore: storage::read().unwrap(),
+    /// Plays back a recorded run instead of reading the mouse.
+    pub fn replay(ctx: &mut Context, config: &Config, replay: Replay) -> tetra::Result<GameScene> {
+        GameScene::build(
+            ctx,
+            config,
+            Simulation::new(replay.seed, replay.tuning.clone(), &assets::sprites()?),
+            Box::new(ReplayInput::new(replay.inputs.clone())),
+            Some(replay),
+        )
+    }
+
+    /// A run of the configured game with `seed`.
+    fn simulation(seed: u64, config: &Config) -> tetra::Result<Simulation> {
+        Ok(Simulation::new(
+            seed,
+            config.tuning.clone(),
+            &assets::sprites()?,
+        ))
+    }
+
+    fn build(
+        ctx: &mut Context,
+        config: &Config,
+        simulation: Simulation,
+        input_source: Box<dyn InputSource>,
+        watching: Option<Replay>,
+    ) -> tetra::Result<GameScene> {
+        let bird_animation = Animation::new(
+            assets::texture(ctx, "./resources/bird.png")?,
+            Bird::frames(),
+            Duration::from_secs_f32(FRAME_LENGTH),
+        );
+
+        Ok(GameScene {
+            sky_texture: assets::texture(ctx, "./resources/sky.png")?,
+            background: Background::new(ctx)?,
+            ground_texture: assets::texture(ctx, "./resources/ground.png")?,
+            pipes_texture: assets::texture(ctx, "./resources/pipes.png")?,
+            finish_line: FinishLine::new(ctx, simulation.tuning().ground_y)?,
+            ghost_animation: bird_animation.clone(),
+            bird_animation,
+            get_ready: assets::texture(ctx, "./resources/get-ready.png")?,
+            instructions: assets::texture(ctx, "./resources/instructions.png")?,
+
+            flap_sound: assets::sound("./resources/flap.wav")?,
+            ground_hit_sound: assets::sound("./resources/ground-hit.wav")?,
+            pipe_hit_sound: assets::sound("./resources/pipe-hit.wav")?,
+            score_sound: assets::sound("./resources/score.wav")?,
+
+            highscore: storage::read(&config.highscore_file).unwrap(),
             score_text: Text::new(
                 "0",
-                Font::vector(ctx, "./resources/font/flappy-font.ttf", 26.0)?,
+                assets::font(ctx, "./resources/font/flappy-font.ttf", 26.0)?,
+            ),
+            level: 1,
+            level_text: Text::new(
+                "LEVEL 1",
+                assets::font(ctx, "./resources/font/flappy-font.ttf", 14.0)?,
+            ),
+
+            input_source,
+            recording: Replay::new(simulation.seed(), simulation.tuning().clone()),
+            watching,
+            retuned: false,
+            testing: false,
+            demo: false,
+            demo_crashed_for: 0.0,
+            pause_choice: Rc::new(Cell::new(PauseChoice::Resume)),
+            countdown: 0.0,
+            countdown_text: Text::new(
+                "",
+                assets::font(ctx, "./resources/font/flappy-font.ttf", 40.0)?,
             ),
+            quitting: false,
 
-            is_mouse_down: true,
-            instructions_visible: true,
-            pipes: Vec::new(),
-            game_over: false,
-            pipe_generator: PipeGenerator::new()?,
+            timestep: FixedTimestep::new(simulation.tuning().tick_rate),
+            ghost: GhostBird::new(
+                GameScene::read_ghost(&config.highscore_file, simulation.tuning()),
+                simulation.tuning(),
+                simulation.sprites().bird_masks.clone(),
+            ),
+            simulation,
+            trace: Vec::new(),
 
             scoreboard: Scoreboard::new(ctx)?,
+            debug_overlay: DebugOverlay::new(ctx)?,
+            notice: Notice::new(ctx)?,
+
+            config: config.clone(),
+            config_watcher: ConfigWatcher::new(),
         })
     }
 
-    fn reset(&mut self) {
-        self.instructions_visible = true;
-        self.pipes.clear();
-        self.background.scroll = true;
-        self.ground.scroll = true;
-        self.bird.reset();
-        self.score = 0;
-        self.game_over = false;
-        self.score_text.set_content(self.score.to_string());
+    /// The ghost of the highscore run, or none if it can't be raced with `tuning`.
+    fn read_ghost(highscore_file: &str, tuning: &Tuning) -> Vec<Pose> {
+        storage::read_ghost(highscore_file, tuning.tick_rate).unwrap_or_else(|err| {
+            eprintln!("Couldn't load the ghost: {}", err);
+            Vec::new()
+        })
     }
 
-    fn start_game(&mut self) {
-        if self.instructions_visible {
-            self.instructions_visible = false;
-        }
-        self.bird.allow_gravity = true;
+    /// Whether the run counts towards the highscore, ghost and replay.
+    fn records(&self) -> bool {
+        self.watching.is_none() && !self.demo && self.simulation.course().is_none()
+    }
+
+    fn pause(&mut self, ctx: &mut Context) -> tetra::Result<Transition> {
+        Ok(Transition::Push(Box::new(PauseScene::new(
+            ctx,
+            self.pause_choice.clone(),
+        )?)))
+    }
 
-        self.pipe_generator.start();
+    fn reset(&mut self) {
+        match &self.watching {
+            Some(replay) => {
+                self.simulation.reset(replay.seed);
+                self.input_source = Box::new(ReplayInput::new(replay.inputs.clone()));
+            }
+            None if self.demo => {
+                self.simulation.reset(self.config.run_seed());
+                self.demo_crashed_for = 0.0;
+            }
+            None => {
+                self.simulation.reset(self.config.run_seed());
+                self.input_source = Box::new(MouseInput::new());
+                self.recording =
+                    Replay::new(self.simulation.seed(), self.simulation.tuning().clone());
+            }
+        }
+        self.retuned = false;
+        self.countdown = 0.0;
+        self.scoreboard.set_cleared(false);
+        self.ghost.reset();
+        self.trace.clear();
+        self.background.scroll = true;
+        self.score_text
+            .set_content(self.simulation.score.to_string());
     }
 
-    fn check_for_collisions(&mut self, ctx: &mut Context) {
-        let mut bird_died = false;
-        if self.bird.alive {
-            for pipe_group in &mut self.pipes {
-                if pipe_group.collides_with(&self.bird.get_collision_rect()) {
-                    bird_died = true;
-                    continue;
+    /// Applies a reloaded config file, or keeps the current settings if it didn't parse.
+    fn reload(&mut self, reloaded: Result<Config, String>) {
+        let config = match reloaded {
+            Ok(config) => config,
+            Err(err) => {
+                self.notice
+                    .show(&format!("{}\nkeeping previous settings", err));
+                return;
+            }
+        };
+
+        // Replays must play back with the tuning they were recorded with.
+        if self.watching.is_none() {
+            let changes = hotreload::live_changes(self.simulation.tuning(), &config.tuning);
+            if !changes.is_empty() {
+                self.simulation.retune(&config.tuning);
+                if self.simulation.started {
+                    self.retuned = true;
+                } else {
+                    self.recording.tuning = self.simulation.tuning().clone();
                 }
+                self.notice.show(&changes.join("\n"));
             }
         }
 
-        if bird_died {
-            assert!(self.pipe_hit_sound.play(ctx).is_ok());
-            self.bird.kill();
+        self.config = config;
+    }
 
-            self.pipe_generator.stop();
-            self.background.scroll = false;
-            self.ground.scroll = false;
+    fn tick(&mut self, ctx: &mut Context) -> tetra::Result {
+        let frame = self.simulation.frame();
+        let mut action = self.input_source.next_input(&self.simulation);
 
-            for pipe_group in &mut self.pipes {
-                pipe_group.enabled = false;
-            }
+        if self.simulation.game_over {
+            action = Input::None;
+        } else {
+            self.recording.record(frame, action);
+        }
+
+        let was_game_over = self.simulation.game_over;
+        let events = self.simulation.step(action);
+
+        if self.simulation.started && !was_game_over {
+            self.trace.push(self.simulation.bird.pose());
+            self.ghost.update();
         }
 
-        if !self.game_over && self.bird.collides_with(&self.ground.get_collision_rect()) {
-            assert!(self.ground_hit_sound.play(ctx).is_ok());
-            self.bird.kill();
-            self.bird.allow_gravity = false;
-            self.background.scroll = false;
-            self.ground.scroll = false;
+        for event in events {
+            self.handle_event(ctx, event)?;
+        }
 
-            self.game_over = true;
-            self.pipe_generator.stop();
+        Ok(())
+    }
 
-            if self.score >= self.highscore {
-                self.highscore = self.score;
-                storage::write(self.highscore).unwrap();
+    fn handle_event(&mut self, ctx: &mut Context, event: Event) -> tetra::Result {
+        match event {
+            Event::Flap => {
+                self.flap_sound.play(ctx)?;
+            }
+            Event::Score => {
+                self.score_sound.play(ctx)?;
+                self.score_text
+                    .set_content(self.simulation.score.to_string());
+            }
+            Event::PipeHit => {
+                self.pipe_hit_sound.play(ctx)?;
+                self.background.scroll = false;
             }
-            self.scoreboard.set_score(ctx, self.score, self.highscore);
+            Event::GroundHit => {
+                self.ground_hit_sound.play(ctx)?;
+                self.background.scroll = false;
+
+                let score = self.simulation.score;
+                if self.records() {
+                    if score >= self.highscore {
+                        self.highscore = score;
+                        let highscore_file = &self.config.highscore_file;
+                        if let Err(err) = storage::write(highscore_file, self.highscore) {
+                            eprintln!("Couldn't save the highscore: {}", err);
+                        }
+                        let tuning = self.simulation.tuning();
+                        if let Err(err) =
+                            storage::write_ghost(highscore_file, &self.trace, tuning.tick_rate)
+                        {
+                            eprintln!("Couldn't save the ghost: {}", err);
+                        }
+                        self.ghost = GhostBird::new(
+                            std::mem::take(&mut self.trace),
+                            tuning,
+                            self.simulation.sprites().bird_masks.clone(),
+                        );
+                    }
+                    if !self.retuned {
+                        if let Err(err) = storage::write_replay(&self.recording) {
+                            eprintln!("Couldn't save the replay: {}", err);
+                        }
+                    }
+                }
+                let caption = match self.simulation.course() {
+                    Some(course) => course.level.name.to_uppercase(),
+                    None => format!("SEED {}", self.simulation.seed()),
+                };
+                self.scoreboard
+                    .set_score(ctx, score, self.highscore, &caption);
+            }
+            Event::Finish => {
+                self.score_sound.play(ctx)?;
+                self.background.scroll = false;
+
+                if let Some(course) = self.simulation.course() {
+                    let level = &course.level;
+                    let stars = level.stars(self.simulation.flaps());
+                    // Without the earlier progress, saving would overwrite it.
+                    let saved = if self.testing {
+                        Ok(())
+                    } else {
+                        storage::read_campaign().and_then(|mut progress| {
+                            if progress.get(&level.id).is_none_or(|best| stars > *best) {
+                                progress.insert(level.id.clone(), stars);
+                                storage::write_campaign(&progress)?;
+                            }
+                            Ok(())
+                        })
+                    };
+                    if let Err(err) = saved {
+                        self.notice.show(&format!("{}\nprogress not saved", err));
+                    }
 
-            for pipe_group in &mut self.pipes {
-                pipe_group.enabled = false;
+                    let caption = format!("CLEARED - {} OF 3 STARS", stars);
+                    self.scoreboard.set_cleared(true);
+                    self.scoreboard
+                        .set_score(ctx, self.simulation.score, self.highscore, &caption);
+                }
             }
         }
+
+        Ok(())
     }
 }
 
 impl Scene for GameScene {
     fn update(&mut self, ctx: &mut Context) -> tetra::Result<Transition> {
-        self.bird.update(ctx);
-
-        if input::is_mouse_button_down(ctx, MouseButton::Left) {
-            if !self.is_mouse_down {
-                let mouse_position = input::get_mouse_position(ctx);
-                if self.instructions_visible {
-                    self.start_game();
-                } else if self.game_over && self.scoreboard.button.contains(mouse_position) {
-                    self.reset();
-                }
-                if self.bird.alive && !self.game_over {
-                    self.flap_sound.play(ctx)?;
-                    self.bird.flap();
-                }
-                self.is_mouse_down = true;
-            }
-        } else {
-            self.is_mouse_down = false;
+        let delta = time::get_delta_time(ctx);
+        self.debug_overlay.update(ctx);
+
+        if let Some(reloaded) = self.config_watcher.poll(delta.as_secs_f32(), &self.config) {
+            self.reload(reloaded);
+        }
+        self.notice.update(delta.as_secs_f32());
+
+        if self.quitting {
+            // Play-tests go back to the editor rather than losing its changes.
+            let transition = if self.testing {
+                Transition::Pop
+            } else {
+                Transition::PopTo(1)
+            };
+            return Ok(transition.animated(Effect::FadeToBlack, self.config.transition_duration));
+        }
+
+        let pressed_pause =
+            input::is_key_pressed(ctx, Key::Escape) || input::is_key_pressed(ctx, Key::P);
+        if pressed_pause && !self.demo {
+            return self.pause(ctx);
         }
 
-        if !self.game_over {
-            for pipe_group in &mut self.pipes {
-                if !pipe_group.has_scored && pipe_group.position.x + 27.0 <= self.bird.position.x {
-                    pipe_group.has_scored = true;
-                    self.score_sound.play(ctx)?;
-                    self.score += 1;
-                    self.score_text.set_content(self.score.to_string());
+        if self.countdown > 0.0 {
+            self.countdown -= delta.as_secs_f32();
+            self.countdown_text
+                .set_content(self.countdown.ceil().max(1.0).to_string());
+            return Ok(Transition::None);
+        }
+        self.input_source.update(ctx);
+
+        if self.demo {
+            if input::is_mouse_button_pressed(ctx, MouseButton::Left) {
+                return Ok(
+                    Transition::Replace(Box::new(GameScene::new(ctx, &self.config)?))
+                        .animated(Effect::Crossfade, self.config.transition_duration),
+                );
+            }
+            if input::get_keys_pressed(ctx).next().is_some() {
+                return Ok(
+                    Transition::Pop.animated(Effect::FadeToBlack, self.config.transition_duration)
+                );
+            }
+            if self.simulation.game_over {
+                self.demo_crashed_for += delta.as_secs_f32();
+                if self.demo_crashed_for >= DEMO_RESTART_DELAY {
+                    self.reset();
                 }
-                pipe_group.update(ctx);
             }
+        } else if self.simulation.game_over {
+            let mouse_position = input::get_mouse_position(ctx);
+            if input::is_mouse_button_pressed(ctx, MouseButton::Left)
+                && self.scoreboard.button.contains(mouse_position)
+            {
+                self.reset();
+            }
+        }
 
-            self.background.update();
-            self.ground.update();
+        for _ in 0..self.timestep.advance(delta) {
+            self.tick(ctx)?;
+        }
 
-            self.check_for_collisions(ctx);
+        if !self.simulation.game_over {
+            self.background.update(
+                self.simulation.difficulty().scroll_speed,
+                delta.as_secs_f32(),
+            );
+        }
 
-            if self.pipe_generator.should_spawn_pipe() {
-                let mut rng = thread_rng();
-                let y: f32 = rng.gen_range(-100.0..100.0);
+        Ok(Transition::None)
+    }
 
-                for pipe_group in &mut self.pipes {
-                    if !pipe_group.alive {
-                        pipe_group.reset(SCREEN_WIDTH as f32, y);
-                        return Ok(Transition::None);
-                    }
-                }
-                let mut pipe_group = PipeGroup::new()?;
-                pipe_group.reset(SCREEN_WIDTH as f32, y);
-                self.pipes.push(pipe_group);
+    fn event(&mut self, ctx: &mut Context, event: tetra::Event) -> tetra::Result<Transition> {
+        match event {
+            // Only a run in progress needs pausing, not the get-ready screen or the scoreboard.
+            tetra::Event::FocusLost
+                if !self.demo && self.simulation.started && !self.simulation.game_over =>
+            {
+                self.pause(ctx)
             }
+            _ => Ok(Transition::None),
         }
+    }
 
-        if input::is_key_pressed(ctx, Key::Escape) {
-            return Ok(Transition::Pop);
+    fn on_resume(&mut self, _ctx: &mut Context) -> tetra::Result {
+        self.input_source.resume();
+        match self.pause_choice.get() {
+            PauseChoice::Resume => {
+                if self.simulation.started && !self.simulation.game_over {
+                    self.countdown = RESUME_COUNTDOWN;
+                }
+            }
+            PauseChoice::Restart => self.reset(),
+            PauseChoice::Quit => self.quitting = true,
         }
 
-        Ok(Transition::None)
+        Ok(())
     }
 
-    fn draw(&mut self, ctx: &mut Context) {
+    fn draw(&mut self, ctx: &mut Context) -> tetra::Result {
+        let center_x = window::get_width(ctx) as f32 / 2.0;
         self.sky_texture.draw(ctx, Vec2::zero());
 
         self.background.draw(ctx);
 
-        if self.instructions_visible {
+        if !self.simulation.started {
             self.instructions.draw(
                 ctx,
                 DrawParams::new()
-                    .position(Vec2::new(SCREEN_WIDTH as f32 / 2.0, 325.0))
+                    .position(Vec2::new(center_x, 325.0))
                     .origin(Vec2::new(
                         self.instructions.width() as f32 / 2.0,
                         self.instructions.height() as f32 / 2.0,
@@ -230,7 +542,7 @@ impl Scene for GameScene {
             self.get_ready.draw(
                 ctx,
                 DrawParams::new()
-                    .position(Vec2::new(SCREEN_WIDTH as f32 / 2.0, 100.0))
+                    .position(Vec2::new(center_x, 100.0))
                     .origin(Vec2::new(
                         self.get_ready.width() as f32 / 2.0,
                         self.get_ready.height() as f32 / 2.0,
@@ -238,24 +550,70 @@ impl Scene for GameScene {
             );
         }
 
-        for pipe_group in &mut self.pipes {
-            pipe_group.draw(ctx, &self.pipes_texture);
+        let blend = self.timestep.blend_factor();
+
+        if let Some(course) = self.simulation.course() {
+            let x = course.finish_x(window::get_width(ctx) as f32, blend);
+            self.finish_line.draw(ctx, x);
+        }
+
+        for pipe_group in &mut self.simulation.pipes {
+            pipe_group.draw(ctx, &self.pipes_texture, blend);
         }
 
-        self.ground.draw(ctx);
+        self.simulation
+            .ground
+            .draw(ctx, &self.ground_texture, blend);
 
-        if !self.game_over {
+        if !self.simulation.game_over {
             let text_bounds = self.score_text.get_bounds(ctx).unwrap();
             self.score_text.draw(
                 ctx,
                 DrawParams::new()
-                    .position(Vec2::new(SCREEN_WIDTH as f32 / 2.0, 10.0))
+                    .position(Vec2::new(center_x, 10.0))
                     .origin(Vec2::new(text_bounds.width / 2.0, 0.0)),
             );
+
+            // Only worth showing when there is a difficulty curve to climb.
+            if !self.simulation.tuning().difficulty.keyframes.is_empty() {
+                let level = self.simulation.level();
+                if level != self.level {
+                    self.level = level;
+                    self.level_text.set_content(format!("LEVEL {}", level));
+                }
+                let level_bounds = self.level_text.get_bounds(ctx).unwrap();
+                self.level_text.draw(
+                    ctx,
+                    DrawParams::new()
+                        .position(Vec2::new(center_x, 14.0 + text_bounds.height))
+                        .origin(Vec2::new(level_bounds.width / 2.0, 0.0)),
+                );
+            }
         } else {
             self.scoreboard.draw(ctx);
         }
 
-        self.bird.draw(ctx);
+        if self.records() {
+            self.ghost.draw(ctx, &mut self.ghost_animation, blend);
+        }
+        self.simulation
+            .bird
+            .draw(ctx, &mut self.bird_animation, blend);
+
+        if self.countdown > 0.0 {
+            let bounds = self.countdown_text.get_bounds(ctx).unwrap();
+            self.countdown_text.draw(
+                ctx,
+                DrawParams::new()
+                    .position(Vec2::new(center_x, 200.0))
+                    .origin(Vec2::new(bounds.width / 2.0, bounds.height / 2.0)),
+            );
+        }
+
+        self.notice.draw(ctx);
+
+        self.debug_overlay.draw(ctx, &mut self.simulation)?;
+
+        Ok(())
     }
 }
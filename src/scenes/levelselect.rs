@@ -0,0 +1,236 @@
+use std::collections::BTreeMap;
+use std::f32::consts::PI;
+
+use tetra::graphics::mesh::{GeometryBuilder, Mesh, ShapeStyle};
+use tetra::graphics::text::Text;
+use tetra::graphics::{Color, DrawParams, Texture};
+use tetra::input::{self, Key, MouseButton};
+use tetra::math::Vec2;
+use tetra::time;
+use tetra::window;
+use tetra::Context;
+
+use crate::prefabs::background::Background;
+use crate::prefabs::button::Button;
+use crate::prefabs::ground::Ground;
+use crate::prefabs::notice::Notice;
+use crate::scenes::{editor::EditorScene, game::GameScene, Scene, Transition};
+use crate::systems::assets;
+use crate::systems::config::Config;
+use crate::systems::level::Level;
+use crate::systems::storage;
+use crate::systems::transitioneffects::Effect;
+
+const FIRST_ROW_Y: f32 = 110.0;
+const ROW_HEIGHT: f32 = 52.0;
+const STAR_RADIUS: f32 = 7.0;
+const EARNED_COLOR: Color = Color::rgb(1.0, 0.8, 0.1);
+const MISSING_COLOR: Color = Color::rgba(0.0, 0.0, 0.0, 0.3);
+
+/// Lists the campaign's levels with the stars earned in each. A level unlocks once the
+/// one before it has been finished. Right-clicking a level opens it in the editor, and
+/// N starts a new one.
+pub struct LevelSelectScene {
+    sky_texture: Texture,
+    background: Background,
+    ground: Ground,
+    ground_texture: Texture,
+    heading: Text,
+    star: Mesh,
+
+    levels: Vec<Level>,
+    buttons: Vec<Button>,
+    stars: BTreeMap<String, u32>,
+    notice: Notice,
+
+    config: Config,
+}
+
+impl LevelSelectScene {
+    pub fn new(
+        ctx: &mut Context,
+        config: Config,
+        levels: Vec<Level>,
+    ) -> tetra::Result<LevelSelectScene> {
+        let heading = Text::new(
+            "SELECT LEVEL",
+            assets::font(ctx, "./resources/font/flappy-font.ttf", 26.0)?,
+        );
+
+        let mut scene = LevelSelectScene {
+            sky_texture: assets::texture(ctx, "./resources/sky.png")?,
+            background: Background::new(ctx)?,
+            ground: Ground::new(config.tuning.ground_y, window::get_width(ctx) as f32),
+            ground_texture: assets::texture(ctx, "./resources/ground.png")?,
+            heading,
+            star: LevelSelectScene::build_star(ctx)?,
+
+            levels,
+            buttons: Vec::new(),
+            stars: BTreeMap::new(),
+            notice: Notice::new(ctx)?,
+
+            config,
+        };
+        scene.refresh(ctx)?;
+
+        Ok(scene)
+    }
+
+    /// A five-pointed star around the origin.
+    fn build_star(ctx: &mut Context) -> tetra::Result<Mesh> {
+        let points: Vec<Vec2<f32>> = (0..10)
+            .map(|index| {
+                let radius = if index % 2 == 0 {
+                    STAR_RADIUS
+                } else {
+                    STAR_RADIUS * 0.45
+                };
+                let angle = index as f32 * PI / 5.0 - PI / 2.0;
+                Vec2::new(angle.cos(), angle.sin()) * radius
+            })
+            .collect();
+
+        GeometryBuilder::new()
+            .polygon(ShapeStyle::Fill, &points)?
+            .build_mesh(ctx)
+    }
+
+    /// Lays out a button per level, and unlocks the levels the stars earned so far lead to.
+    fn refresh(&mut self, ctx: &mut Context) -> tetra::Result {
+        let center_x = window::get_width(ctx) as f32 / 2.0;
+        self.buttons = self
+            .levels
+            .iter()
+            .enumerate()
+            .map(|(index, level)| {
+                let y = FIRST_ROW_Y + index as f32 * ROW_HEIGHT;
+                let label = format!("{}. {}", index + 1, level.name.to_uppercase());
+                Button::with_label(ctx, Vec2::new(center_x, y), &label)
+            })
+            .collect::<tetra::Result<Vec<Button>>>()?;
+
+        // Only the first level is unlocked if the progress can't be read.
+        self.stars = storage::read_campaign().unwrap_or_else(|err| {
+            self.notice.show(&format!("{}\nprogress not loaded", err));
+            BTreeMap::new()
+        });
+        for (index, button) in self.buttons.iter_mut().enumerate() {
+            button.enabled = index == 0
+                || self
+                    .stars
+                    .get(&self.levels[index - 1].id)
+                    .is_some_and(|stars| *stars > 0);
+        }
+
+        Ok(())
+    }
+}
+
+impl Scene for LevelSelectScene {
+    fn update(&mut self, ctx: &mut Context) -> tetra::Result<Transition> {
+        let dt = time::get_delta_time(ctx).as_secs_f32();
+        let scroll_speed = self.config.tuning.scroll_speed;
+        self.background.update(scroll_speed, dt);
+        self.ground.update(scroll_speed, dt);
+        self.notice.update(dt);
+
+        if input::is_mouse_button_pressed(ctx, MouseButton::Left) {
+            let mouse_position = input::get_mouse_position(ctx);
+            let chosen = self
+                .buttons
+                .iter_mut()
+                .position(|button| button.contains(mouse_position));
+            if let Some(index) = chosen {
+                return Ok(Transition::Push(Box::new(GameScene::level(
+                    ctx,
+                    &self.config,
+                    self.levels[index].clone(),
+                )?))
+                .animated(Effect::Wipe, self.config.transition_duration));
+            }
+        }
+
+        if input::is_mouse_button_pressed(ctx, MouseButton::Right) {
+            let mouse_position = input::get_mouse_position(ctx);
+            // Locked levels can be edited too.
+            let chosen = self.buttons.iter_mut().position(|button| {
+                let enabled = button.enabled;
+                button.enabled = true;
+                let contains = button.contains(mouse_position);
+                button.enabled = enabled;
+                contains
+            });
+            if let Some(index) = chosen {
+                return Ok(Transition::Push(Box::new(EditorScene::new(
+                    ctx,
+                    self.config.clone(),
+                    self.levels[index].clone(),
+                )?))
+                .animated(Effect::Crossfade, self.config.transition_duration));
+            }
+        }
+
+        if input::is_key_pressed(ctx, Key::N) {
+            let id = format!("{:02}-untitled", self.levels.len() + 1);
+            return Ok(Transition::Push(Box::new(EditorScene::new(
+                ctx,
+                self.config.clone(),
+                Level::empty(id),
+            )?))
+            .animated(Effect::Crossfade, self.config.transition_duration));
+        }
+
+        if input::is_key_pressed(ctx, Key::Escape) {
+            return Ok(
+                Transition::PopTo(1).animated(Effect::FadeToBlack, self.config.transition_duration)
+            );
+        }
+
+        Ok(Transition::None)
+    }
+
+    /// Levels may have been edited or finished since, so they are read again.
+    fn on_resume(&mut self, ctx: &mut Context) -> tetra::Result {
+        match Level::campaign() {
+            Ok(levels) => self.levels = levels,
+            Err(err) => eprintln!("Couldn't reload the campaign: {}", err),
+        }
+        self.refresh(ctx)
+    }
+
+    fn draw(&mut self, ctx: &mut Context) -> tetra::Result {
+        let center_x = window::get_width(ctx) as f32 / 2.0;
+        self.sky_texture.draw(ctx, Vec2::zero());
+        self.background.draw(ctx);
+        self.ground.draw(ctx, &self.ground_texture, 1.0);
+
+        let heading_width = self.heading.get_bounds(ctx).unwrap().width;
+        self.heading.draw(
+            ctx,
+            Vec2::new(center_x - heading_width / 2.0, FIRST_ROW_Y - 60.0),
+        );
+
+        for (index, button) in self.buttons.iter_mut().enumerate() {
+            button.draw(ctx);
+
+            let earned = self.stars.get(&self.levels[index].id).copied().unwrap_or(0);
+            let y = FIRST_ROW_Y + index as f32 * ROW_HEIGHT + 20.0;
+            for star in 0..3 {
+                let x = center_x + (star as f32 - 1.0) * STAR_RADIUS * 2.5;
+                let color = if star < earned {
+                    EARNED_COLOR
+                } else {
+                    MISSING_COLOR
+                };
+                self.star.draw(
+                    ctx,
+                    DrawParams::new().position(Vec2::new(x, y)).color(color),
+                );
+            }
+        }
+        self.notice.draw(ctx);
+
+        Ok(())
+    }
+}
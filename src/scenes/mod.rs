@@ -1,14 +1,94 @@
+pub mod editor;
 pub mod game;
+pub mod levelselect;
+pub mod pause;
+pub mod preloader;
 pub mod title;
-use tetra::Context;
+use tetra::{Context, Event};
 
+use crate::systems::transitioneffects::Effect;
+
+/// A screen of the game, or an overlay on top of one. `SceneManager` keeps them in a
+/// stack, drawing up from the topmost opaque scene and updating up from the topmost
+/// scene that blocks updates.
 pub trait Scene {
+    /// Called once a frame. Only the transition of the scene on top is applied, so a
+    /// scene that keeps updating under a non-blocking one can't change the stack until
+    /// it is on top again.
     fn update(&mut self, ctx: &mut Context) -> tetra::Result<Transition>;
-    fn draw(&mut self, ctx: &mut Context);
+    fn draw(&mut self, ctx: &mut Context) -> tetra::Result;
+
+    /// Called with window and input events while the scene is on top.
+    fn event(&mut self, _ctx: &mut Context, _event: Event) -> tetra::Result<Transition> {
+        Ok(Transition::None)
+    }
+
+    /// Whether the scene covers the whole screen. The scenes below transparent ones are
+    /// drawn first.
+    fn is_opaque(&self) -> bool {
+        true
+    }
+
+    /// Whether the scenes below stop updating while this one is above them.
+    fn blocks_updates(&self) -> bool {
+        true
+    }
+
+    /// Called once the scene has been put on the stack, before its first update.
+    fn on_enter(&mut self, _ctx: &mut Context) -> tetra::Result {
+        Ok(())
+    }
+
+    /// Called just before the scene is taken off the stack.
+    fn on_exit(&mut self, _ctx: &mut Context) -> tetra::Result {
+        Ok(())
+    }
+
+    /// Called when another scene is pushed on top of this one, even if it keeps
+    /// updating below it.
+    fn on_pause(&mut self, _ctx: &mut Context) -> tetra::Result {
+        Ok(())
+    }
+
+    /// Called when this scene is back on top, after the scenes above it have exited.
+    fn on_resume(&mut self, _ctx: &mut Context) -> tetra::Result {
+        Ok(())
+    }
 }
 
+/// What the scene on top asks `SceneManager` to do after an update. Scenes that leave
+/// the stack exit top first, and a scene that ends up back on top only resumes once
+/// they all have.
 pub enum Transition {
     None,
+    /// Pauses the current scene and enters the new one on top of it.
     Push(Box<dyn Scene>),
+    /// Exits the current scene and resumes the one below, or quits if there is none.
     Pop,
+    /// Exits the current scene and enters the new one in its place. The scene below
+    /// neither resumes nor pauses again.
+    Replace(Box<dyn Scene>),
+    /// Exits scenes until only this many are left, then resumes the new top, e.g.
+    /// `PopTo(1)` to go back to the title screen. Does nothing if there aren't more
+    /// than that.
+    PopTo(usize),
+    /// Exits every scene and closes the window.
+    Quit,
+    /// Carries out `transition` while `effect` plays for `duration` seconds, from what
+    /// was on screen before to what is on screen after.
+    Animated {
+        transition: Box<Transition>,
+        effect: Effect,
+        duration: f32,
+    },
+}
+
+impl Transition {
+    pub fn animated(self, effect: Effect, duration: f32) -> Transition {
+        Transition::Animated {
+            transition: Box::new(self),
+            effect,
+            duration,
+        }
+    }
 }
@@ -0,0 +1,156 @@
+use std::cell::Cell;
+use std::rc::Rc;
+
+use tetra::audio;
+use tetra::graphics::mesh::{Mesh, ShapeStyle};
+use tetra::graphics::text::Text;
+use tetra::graphics::{Color, DrawParams, Rectangle};
+use tetra::input::{self, Key, MouseButton};
+use tetra::math::Vec2;
+use tetra::window;
+use tetra::Context;
+
+use crate::prefabs::button::Button;
+use crate::scenes::{Scene, Transition};
+use crate::systems::assets;
+
+const SHADE_COLOR: Color = Color::rgba(0.0, 0.0, 0.0, 0.5);
+const FIRST_BUTTON_Y: f32 = 190.0;
+const BUTTON_SPACING: f32 = 45.0;
+
+/// What the game should do once the pause menu is closed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PauseChoice {
+    Resume,
+    Restart,
+    Quit,
+}
+
+/// A menu over the paused game. It closes with the player's choice left in `choice`,
+/// for the game to act on when it resumes.
+pub struct PauseScene {
+    shade: Mesh,
+    heading: Text,
+    resume_button: Button,
+    restart_button: Button,
+    settings_button: Button,
+    quit_button: Button,
+    sound_button: Button,
+    back_button: Button,
+    /// Whether the settings are shown instead of the menu.
+    in_settings: bool,
+
+    choice: Rc<Cell<PauseChoice>>,
+}
+
+impl PauseScene {
+    pub fn new(ctx: &mut Context, choice: Rc<Cell<PauseChoice>>) -> tetra::Result<PauseScene> {
+        let width = window::get_width(ctx) as f32;
+        let height = window::get_height(ctx) as f32;
+        let row = |index: f32| Vec2::new(width / 2.0, FIRST_BUTTON_Y + index * BUTTON_SPACING);
+        choice.set(PauseChoice::Resume);
+
+        Ok(PauseScene {
+            shade: Mesh::rectangle(
+                ctx,
+                ShapeStyle::Fill,
+                Rectangle::new(0.0, 0.0, width, height),
+            )?,
+            heading: Text::new(
+                "PAUSED",
+                assets::font(ctx, "./resources/font/flappy-font.ttf", 26.0)?,
+            ),
+            resume_button: Button::with_label(ctx, row(0.0), "Resume")?,
+            restart_button: Button::with_label(ctx, row(1.0), "Restart")?,
+            settings_button: Button::with_label(ctx, row(2.0), "Settings")?,
+            quit_button: Button::with_label(ctx, row(3.0), "Quit")?,
+            sound_button: PauseScene::sound_button(ctx, row(0.0))?,
+            back_button: Button::with_label(ctx, row(1.0), "Back")?,
+            in_settings: false,
+
+            choice,
+        })
+    }
+
+    fn sound_button(ctx: &mut Context, position: Vec2<f32>) -> tetra::Result<Button> {
+        let label = if audio::get_master_volume(ctx) > 0.0 {
+            "Sound: on"
+        } else {
+            "Sound: off"
+        };
+        Button::with_label(ctx, position, label)
+    }
+
+    fn close(&self, choice: PauseChoice) -> tetra::Result<Transition> {
+        self.choice.set(choice);
+        Ok(Transition::Pop)
+    }
+}
+
+impl Scene for PauseScene {
+    fn update(&mut self, ctx: &mut Context) -> tetra::Result<Transition> {
+        let clicked = input::is_mouse_button_pressed(ctx, MouseButton::Left);
+        let mouse_position = input::get_mouse_position(ctx);
+
+        if self.in_settings {
+            if clicked && self.sound_button.contains(mouse_position) {
+                let volume = if audio::get_master_volume(ctx) > 0.0 {
+                    0.0
+                } else {
+                    1.0
+                };
+                audio::set_master_volume(ctx, volume);
+                let position = Vec2::new(window::get_width(ctx) as f32 / 2.0, FIRST_BUTTON_Y);
+                self.sound_button = PauseScene::sound_button(ctx, position)?;
+            } else if clicked && self.back_button.contains(mouse_position)
+                || input::is_key_pressed(ctx, Key::Escape)
+            {
+                self.in_settings = false;
+            }
+            return Ok(Transition::None);
+        }
+
+        if clicked && self.resume_button.contains(mouse_position)
+            || input::is_key_pressed(ctx, Key::Escape)
+            || input::is_key_pressed(ctx, Key::P)
+        {
+            self.close(PauseChoice::Resume)
+        } else if clicked && self.restart_button.contains(mouse_position) {
+            self.close(PauseChoice::Restart)
+        } else if clicked && self.quit_button.contains(mouse_position) {
+            self.close(PauseChoice::Quit)
+        } else {
+            if clicked && self.settings_button.contains(mouse_position) {
+                self.in_settings = true;
+            }
+            Ok(Transition::None)
+        }
+    }
+
+    fn is_opaque(&self) -> bool {
+        false
+    }
+
+    fn draw(&mut self, ctx: &mut Context) -> tetra::Result {
+        self.shade.draw(ctx, DrawParams::new().color(SHADE_COLOR));
+
+        let center_x = window::get_width(ctx) as f32 / 2.0;
+        let heading_width = self.heading.get_bounds(ctx).unwrap().width;
+        self.heading.draw(
+            ctx,
+            Vec2::new(center_x - heading_width / 2.0, FIRST_BUTTON_Y - 80.0),
+        );
+
+        if self.in_settings {
+            self.sound_button.draw(ctx);
+            self.back_button.draw(ctx);
+        } else {
+            self.resume_button.draw(ctx);
+            self.restart_button.draw(ctx);
+            self.settings_button.draw(ctx);
+            self.quit_button.draw(ctx);
+        }
+
+        Ok(())
+    }
+}
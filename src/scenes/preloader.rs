@@ -0,0 +1,64 @@
+use tetra::graphics::{Rectangle, Texture};
+use tetra::math::Vec2;
+use tetra::window;
+use tetra::Context;
+
+use crate::scenes::{title::TitleScene, Scene, Transition};
+use crate::systems::assets::Loader;
+use crate::systems::config::Config;
+use crate::systems::transitioneffects::Effect;
+
+/// Shows a bar filling up while `Loader` loads the assets, then gives way to the title
+/// screen.
+pub struct PreloaderScene {
+    bar: Texture,
+    loader: Loader,
+    /// Makes the scene to go on to, once everything is loaded.
+    next: fn(&mut Context, Config) -> tetra::Result<TitleScene>,
+
+    config: Config,
+}
+
+impl PreloaderScene {
+    pub fn new(
+        ctx: &mut Context,
+        config: Config,
+        next: fn(&mut Context, Config) -> tetra::Result<TitleScene>,
+    ) -> tetra::Result<PreloaderScene> {
+        Ok(PreloaderScene {
+            bar: Texture::new(ctx, "./resources/preloader.gif")?,
+            loader: Loader::start(),
+            next,
+
+            config,
+        })
+    }
+}
+
+impl Scene for PreloaderScene {
+    fn update(&mut self, ctx: &mut Context) -> tetra::Result<Transition> {
+        self.loader.upload(ctx)?;
+        if !self.loader.is_finished() {
+            return Ok(Transition::None);
+        }
+
+        let next = (self.next)(ctx, self.config.clone())?;
+        Ok(Transition::Replace(Box::new(next))
+            .animated(Effect::FadeToBlack, self.config.transition_duration))
+    }
+
+    fn draw(&mut self, ctx: &mut Context) -> tetra::Result {
+        let (width, height) = (self.bar.width() as f32, self.bar.height() as f32);
+        let position = Vec2::new(
+            (window::get_width(ctx) as f32 - width) / 2.0,
+            (window::get_height(ctx) as f32 - height) / 2.0,
+        );
+        self.bar.draw_region(
+            ctx,
+            Rectangle::new(0.0, 0.0, width * self.loader.progress(), height),
+            position,
+        );
+
+        Ok(())
+    }
+}
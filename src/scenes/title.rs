@@ -3,13 +3,23 @@ use tetra::graphics::animation::Animation;
 use tetra::graphics::{Rectangle, Texture};
 use tetra::input::{self, Key, MouseButton};
 use tetra::math::Vec2;
+use tetra::time;
+use tetra::window;
 use tetra::Context;
 
 use crate::prefabs::background::Background;
 use crate::prefabs::button::Button;
 use crate::prefabs::ground::Ground;
-use crate::scenes::{game::GameScene, Scene, Transition};
-use crate::SCREEN_WIDTH;
+use crate::prefabs::notice::Notice;
+use crate::scenes::{game::GameScene, levelselect::LevelSelectScene, Scene, Transition};
+use crate::systems::assets;
+use crate::systems::config::Config;
+use crate::systems::level::Level;
+use crate::systems::storage;
+use crate::systems::transitioneffects::Effect;
+
+/// How long the title screen waits for the mouse before starting the demo, in seconds.
+const ATTRACT_DELAY: f32 = 15.0;
 
 pub struct TitleScene {
     sky_texture: Texture,
@@ -17,55 +27,146 @@ pub struct TitleScene {
     bird: Animation,
     background: Background,
     ground: Ground,
+    ground_texture: Texture,
     button: Button,
+    replay_button: Button,
+    campaign_button: Button,
+    demo_button: Button,
+    notice: Notice,
+    /// Seconds since the mouse last moved or was clicked.
+    idle_time: f32,
+    mouse_position: Vec2<f32>,
+
+    config: Config,
 }
 
 impl TitleScene {
-    pub fn new(ctx: &mut Context) -> tetra::Result<TitleScene> {
+    pub fn new(ctx: &mut Context, config: Config) -> tetra::Result<TitleScene> {
+        let center_x = window::get_width(ctx) as f32 / 2.0;
+
         Ok(TitleScene {
-            sky_texture: Texture::new(ctx, "./resources/sky.png")?,
-            title: Texture::new(ctx, "./resources/title.png")?,
+            sky_texture: assets::texture(ctx, "./resources/sky.png")?,
+            title: assets::texture(ctx, "./resources/title.png")?,
 
             bird: Animation::new(
-                Texture::new(ctx, "./resources/bird.png")?,
+                assets::texture(ctx, "./resources/bird.png")?,
                 Rectangle::row(0.0, 0.0, 34.0, 24.0).take(3).collect(),
                 Duration::from_secs_f64(0.2),
             ),
             background: Background::new(ctx)?,
-            ground: Ground::new(ctx)?,
+            ground: Ground::new(config.tuning.ground_y, window::get_width(ctx) as f32),
+            ground_texture: assets::texture(ctx, "./resources/ground.png")?,
 
-            button: Button::new(ctx, Vec2::new(SCREEN_WIDTH as f32 / 2.0, 300.0))?,
+            button: Button::new(ctx, Vec2::new(center_x, 300.0))?,
+            replay_button: Button::with_label(ctx, Vec2::new(center_x, 355.0), "Watch replay")?,
+            campaign_button: Button::with_label(ctx, Vec2::new(center_x, 230.0), "Campaign")?,
+            demo_button: Button::with_label(ctx, Vec2::new(center_x, 385.0), "Watch demo")?,
+            notice: Notice::new(ctx)?,
+            idle_time: 0.0,
+            mouse_position: Vec2::zero(),
+
+            config,
         })
     }
+
+    /// The title screen, already idle for long enough to start the demo.
+    pub fn attract(ctx: &mut Context, config: Config) -> tetra::Result<TitleScene> {
+        let mut title = TitleScene::new(ctx, config)?;
+        title.idle_time = ATTRACT_DELAY;
+        Ok(title)
+    }
 }
 
 impl Scene for TitleScene {
     fn update(&mut self, ctx: &mut Context) -> tetra::Result<Transition> {
-        self.background.update();
-        self.ground.update();
+        let dt = time::get_delta_time(ctx).as_secs_f32();
+        let scroll_speed = self.config.tuning.scroll_speed;
+        self.background.update(scroll_speed, dt);
+        self.ground.update(scroll_speed, dt);
+        self.notice.update(dt);
 
         let mouse_position = input::get_mouse_position(ctx);
+        // A fresh press, so the click that left the scene above doesn't press a button here.
+        let clicked = input::is_mouse_button_pressed(ctx, MouseButton::Left);
         if input::is_mouse_button_down(ctx, MouseButton::Left)
-            && self.button.contains(mouse_position)
+            || mouse_position != self.mouse_position
         {
-            Ok(Transition::Push(Box::new(GameScene::new(ctx)?)))
+            self.idle_time = 0.0;
+            self.mouse_position = mouse_position;
+        } else {
+            self.idle_time += dt;
+        }
+
+        if self.idle_time >= ATTRACT_DELAY || clicked && self.demo_button.contains(mouse_position) {
+            self.idle_time = 0.0;
+            Ok(
+                Transition::Push(Box::new(GameScene::demo(ctx, &self.config)?))
+                    .animated(Effect::Crossfade, self.config.transition_duration),
+            )
+        } else if clicked && self.button.contains(mouse_position) {
+            Ok(
+                Transition::Push(Box::new(GameScene::new(ctx, &self.config)?))
+                    .animated(Effect::Slide, self.config.transition_duration),
+            )
+        } else if clicked && self.replay_button.contains(mouse_position) {
+            match storage::read_replay() {
+                Ok(Some(replay)) => {
+                    Ok(
+                        Transition::Push(Box::new(GameScene::replay(ctx, &self.config, replay)?))
+                            .animated(Effect::Slide, self.config.transition_duration),
+                    )
+                }
+                Ok(None) => {
+                    self.notice.show("No run recorded yet");
+                    Ok(Transition::None)
+                }
+                Err(err) => {
+                    eprintln!("Couldn't load the replay: {}", err);
+                    self.notice.show("Couldn't load the replay");
+                    Ok(Transition::None)
+                }
+            }
+        } else if clicked && self.campaign_button.contains(mouse_position) {
+            match Level::campaign() {
+                Ok(levels) => Ok(Transition::Push(Box::new(LevelSelectScene::new(
+                    ctx,
+                    self.config.clone(),
+                    levels,
+                )?))
+                .animated(Effect::Slide, self.config.transition_duration)),
+                Err(err) => {
+                    eprintln!("Couldn't load the campaign: {}", err);
+                    Ok(Transition::None)
+                }
+            }
         } else if input::is_key_pressed(ctx, Key::Escape) {
-            Ok(Transition::Pop)
+            Ok(Transition::Quit)
         } else {
             Ok(Transition::None)
         }
     }
 
-    fn draw(&mut self, ctx: &mut Context) {
+    fn on_resume(&mut self, _ctx: &mut Context) -> tetra::Result {
+        self.idle_time = 0.0;
+        Ok(())
+    }
+
+    fn draw(&mut self, ctx: &mut Context) -> tetra::Result {
         self.sky_texture.draw(ctx, Vec2::new(0.0, 0.0));
 
         self.background.draw(ctx);
-        self.ground.draw(ctx);
+        self.ground.draw(ctx, &self.ground_texture, 1.0);
 
         self.bird.draw(ctx, Vec2::new(230.0, 105.0));
 
         self.title.draw(ctx, Vec2::new(30.0, 100.0));
 
         self.button.draw(ctx);
+        self.replay_button.draw(ctx);
+        self.campaign_button.draw(ctx);
+        self.demo_button.draw(ctx);
+        self.notice.draw(ctx);
+
+        Ok(())
     }
 }
@@ -0,0 +1,216 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::iter;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+
+use tetra::audio::Sound;
+use tetra::graphics::text::{Font, VectorFontBuilder};
+use tetra::graphics::{ImageData, Texture};
+use tetra::{Context, TetraError};
+
+use crate::systems::sprites::Sprites;
+
+/// Every texture the scenes draw, loaded up front by `Loader`.
+const TEXTURES: &[&str] = &[
+    BIRD_IMAGE,
+    "./resources/cityscape.png",
+    "./resources/clouds.png",
+    "./resources/gameover.png",
+    "./resources/get-ready.png",
+    "./resources/ground.png",
+    "./resources/instructions.png",
+    "./resources/medals.png",
+    PIPES_IMAGE,
+    "./resources/scoreboard.png",
+    "./resources/sky.png",
+    "./resources/start-button.png",
+    "./resources/title.png",
+    "./resources/trees.png",
+];
+const BIRD_IMAGE: &str = "./resources/bird.png";
+const PIPES_IMAGE: &str = "./resources/pipes.png";
+/// Every sound the game plays, loaded up front by `Loader`.
+const SOUNDS: &[&str] = &[
+    "./resources/flap.wav",
+    "./resources/ground-hit.wav",
+    "./resources/pipe-hit.wav",
+    "./resources/score.wav",
+];
+/// Every font the scenes write with, loaded up front by `Loader`.
+const FONTS: &[&str] = &["./resources/font/flappy-font.ttf"];
+/// What the collision data of `bird.png` and `pipes.png` is cached under.
+const SPRITES: &str = "sprites";
+
+/// The assets loaded so far, by path. Textures and fonts only work on the thread that
+/// made them, so this is kept per thread and only the main one fills it.
+#[derive(Default)]
+struct Cache {
+    textures: HashMap<String, Texture>,
+    sounds: HashMap<String, Sound>,
+    fonts: HashMap<String, VectorFontBuilder>,
+    sprites: HashMap<String, Sprites>,
+}
+
+thread_local! {
+    static CACHE: RefCell<Cache> = RefCell::new(Cache::default());
+}
+
+/// The texture at `path`, loaded now unless it already has been.
+pub fn texture(ctx: &mut Context, path: &str) -> tetra::Result<Texture> {
+    cached(
+        |cache| &mut cache.textures,
+        path,
+        || Texture::new(ctx, path),
+    )
+}
+
+/// The sound at `path`, loaded now unless it already has been.
+pub fn sound(path: &str) -> tetra::Result<Sound> {
+    cached(|cache| &mut cache.sounds, path, || Sound::new(path))
+}
+
+/// The vector font at `path` in `size`, read now unless it already has been.
+pub fn font(ctx: &mut Context, path: &str, size: f32) -> tetra::Result<Font> {
+    cached(
+        |cache| &mut cache.fonts,
+        path,
+        || VectorFontBuilder::new(path),
+    )?
+    .with_size(ctx, size)
+}
+
+/// The collision data of the sprites, read now unless it already has been.
+pub fn sprites() -> tetra::Result<Sprites> {
+    cached(|cache| &mut cache.sprites, SPRITES, Sprites::load)
+}
+
+fn cached<T: Clone>(
+    assets: fn(&mut Cache) -> &mut HashMap<String, T>,
+    path: &str,
+    load: impl FnOnce() -> tetra::Result<T>,
+) -> tetra::Result<T> {
+    let loaded = CACHE.with(|cache| assets(&mut cache.borrow_mut()).get(path).cloned());
+    if let Some(asset) = loaded {
+        return Ok(asset);
+    }
+
+    let asset = load()?;
+    CACHE.with(|cache| {
+        assets(&mut cache.borrow_mut()).insert(path.to_string(), asset.clone());
+    });
+    Ok(asset)
+}
+
+/// An asset read and decoded on the loading thread, ready to be handed to the GPU.
+enum Decoded {
+    Texture(ImageData),
+    Sound(Sound),
+    /// tetra only keeps font data it read itself, so fonts are read on the main thread.
+    Font,
+    Sprites(Sprites),
+}
+
+/// Loads every asset the game uses into the cache without freezing the window. The
+/// files are read and decoded on another thread, and `upload` finishes them on the
+/// main one as they come in.
+pub struct Loader {
+    decoded: Receiver<(&'static str, tetra::Result<Decoded>)>,
+    loaded: usize,
+}
+
+impl Loader {
+    pub fn start() -> Loader {
+        let (sender, decoded) = mpsc::channel();
+        thread::spawn(move || {
+            // The loader is gone if nobody is listening, so there's no point going on.
+            let send = |asset| sender.send(asset).is_ok();
+
+            // The collision data comes from the same images as the textures, so those two
+            // are kept instead of being decoded again.
+            let (mut bird, mut pipes) = (None, None);
+            for &path in TEXTURES {
+                let data = ImageData::from_file(path);
+                if let Ok(data) = &data {
+                    match path {
+                        BIRD_IMAGE => bird = Some(data.clone()),
+                        PIPES_IMAGE => pipes = Some(data.clone()),
+                        _ => {}
+                    }
+                }
+                if !send((path, data.map(Decoded::Texture))) {
+                    return;
+                }
+            }
+
+            let sounds = SOUNDS
+                .iter()
+                .map(|&path| (path, Sound::new(path).map(Decoded::Sound)));
+            let fonts = FONTS.iter().map(|&path| (path, Ok(Decoded::Font)));
+            let sprites = iter::once_with(|| {
+                let sprites = match (&bird, &pipes) {
+                    (Some(bird), Some(pipes)) => Ok(Sprites::from_images(bird, pipes)),
+                    _ => Err(TetraError::PlatformError(
+                        "the sprites' collision data needs bird.png and pipes.png".to_string(),
+                    )),
+                };
+                (SPRITES, sprites.map(Decoded::Sprites))
+            });
+
+            for asset in sounds.chain(fonts).chain(sprites) {
+                if !send(asset) {
+                    return;
+                }
+            }
+        });
+
+        Loader { decoded, loaded: 0 }
+    }
+
+    /// Adds the assets decoded since the last call to the cache. Fails if the loading
+    /// thread stopped before sending everything.
+    pub fn upload(&mut self, ctx: &mut Context) -> tetra::Result {
+        loop {
+            let (path, decoded) = match self.decoded.try_recv() {
+                Ok(asset) => asset,
+                Err(TryRecvError::Disconnected) if !self.is_finished() => {
+                    return Err(TetraError::PlatformError(
+                        "the asset loader stopped before loading everything".to_string(),
+                    ))
+                }
+                Err(_) => return Ok(()),
+            };
+
+            match decoded? {
+                Decoded::Texture(data) => {
+                    let texture = Texture::from_image_data(ctx, &data)?;
+                    cached(|cache| &mut cache.textures, path, || Ok(texture))?;
+                }
+                Decoded::Sound(sound) => {
+                    cached(|cache| &mut cache.sounds, path, || Ok(sound))?;
+                }
+                Decoded::Font => {
+                    cached(
+                        |cache| &mut cache.fonts,
+                        path,
+                        || VectorFontBuilder::new(path),
+                    )?;
+                }
+                Decoded::Sprites(sprites) => {
+                    cached(|cache| &mut cache.sprites, path, || Ok(sprites))?;
+                }
+            }
+            self.loaded += 1;
+        }
+    }
+
+    /// How much has been loaded, from 0 to 1.
+    pub fn progress(&self) -> f32 {
+        // The sprites' collision data is one more.
+        self.loaded as f32 / (TEXTURES.len() + SOUNDS.len() + FONTS.len() + 1) as f32
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.progress() >= 1.0
+    }
+}
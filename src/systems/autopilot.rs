@@ -0,0 +1,74 @@
+use std::ops::Range;
+
+use crate::prefabs::pipes::GAP_CENTER;
+use crate::systems::controls::InputSource;
+use crate::systems::reachability::PIPES_PER_SEED;
+use crate::systems::simulation::{Input, Simulation};
+use crate::systems::sprites::Sprites;
+use crate::systems::tuning::Tuning;
+
+/// Flies the bird by itself, bobbing around the middle of the next gap: it flaps
+/// whenever the bird would otherwise sink far enough below the middle that a flap only
+/// just brings it back as far above.
+pub struct Autopilot;
+
+impl Autopilot {
+    pub fn new() -> Autopilot {
+        Autopilot
+    }
+
+    /// Where the bird should be, which is the middle of the first gap it hasn't
+    /// cleared yet.
+    fn target(simulation: &Simulation) -> f32 {
+        simulation
+            .upcoming_pipes()
+            .first()
+            .map_or(GAP_CENTER, |pipe_group| pipe_group.gap_center().y)
+    }
+}
+
+impl InputSource for Autopilot {
+    fn next_input(&mut self, simulation: &Simulation) -> Input {
+        // The first flap starts the run.
+        if !simulation.started {
+            return Input::Flap;
+        }
+
+        let tuning = simulation.tuning();
+        let dt = tuning.tick_length();
+        let flap_height = tuning.flap_speed * tuning.flap_speed / (2.0 * tuning.gravity);
+        let lowest = Autopilot::target(simulation) + flap_height / 2.0;
+
+        // A flap takes effect after the bird has moved, so look one step ahead, and
+        // flap on whichever step leaves the bird closest to the lowest point.
+        let velocity = simulation.bird.velocity().y + tuning.gravity * dt;
+        let next_y = simulation.bird.position.y + velocity * dt;
+        let after_next_y = next_y + (velocity + tuning.gravity * dt) * dt;
+        if next_y > lowest || after_next_y - lowest > lowest - next_y {
+            Input::Flap
+        } else {
+            Input::None
+        }
+    }
+}
+
+/// Lets the autopilot fly every seed in `seeds`, and returns the seeds where it crashed
+/// before passing `PIPES_PER_SEED` pipes, each with the score it got.
+pub fn check_seeds(tuning: &Tuning, sprites: &Sprites, seeds: Range<u64>) -> Vec<(u64, i32)> {
+    let mut simulation = Simulation::new(seeds.start, tuning.clone(), sprites);
+    let mut autopilot = Autopilot::new();
+    let mut crashes = Vec::new();
+
+    for seed in seeds {
+        simulation.reset(seed);
+        while !simulation.game_over && simulation.score < PIPES_PER_SEED {
+            let input = autopilot.next_input(&simulation);
+            simulation.step(input);
+        }
+        if simulation.score < PIPES_PER_SEED {
+            crashes.push((seed, simulation.score));
+        }
+    }
+
+    crashes
+}
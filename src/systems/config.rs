@@ -0,0 +1,345 @@
+use std::fs;
+use std::io::ErrorKind;
+use std::iter;
+use std::path::Path;
+
+use rand::{thread_rng, Rng};
+use serde::de::{Deserializer, Error};
+use serde::Deserialize;
+use toml::{Table, Value};
+
+use crate::prefabs::bird::BIRD_HEIGHT;
+use crate::systems::storage;
+use crate::systems::tuning::Tuning;
+use crate::{CONFIG_FILE_NAME, FILE_NAME};
+
+/// How long scene changes take to animate unless configured otherwise, in seconds.
+const TRANSITION_DURATION: f32 = 0.4;
+
+/// Everything that can be set from `flappy.toml` or the command line. Anything left
+/// out keeps its default, which matches the original game.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub seed: Option<u64>,
+    #[serde(deserialize_with = "file_name")]
+    pub highscore_file: String,
+    pub tuning: Tuning,
+    /// How long scene changes take to animate, in seconds. Zero cuts straight away.
+    #[serde(deserialize_with = "duration")]
+    pub transition_duration: f32,
+    /// The command line overrides, applied again whenever the file is reloaded.
+    #[serde(skip)]
+    pub args: Vec<String>,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            seed: None,
+            highscore_file: FILE_NAME.to_string(),
+            tuning: Tuning::default(),
+            transition_duration: TRANSITION_DURATION,
+            args: Vec::new(),
+        }
+    }
+}
+
+impl Config {
+    /// Reads the config file from the working directory if there is one, then applies
+    /// the `--key value` overrides in `args`.
+    pub fn load(args: Vec<String>) -> Result<Config, String> {
+        let content = match fs::read_to_string(CONFIG_FILE_NAME) {
+            Ok(content) => content,
+            Err(err) if err.kind() == ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(format!("{}: {}", CONFIG_FILE_NAME, err)),
+        };
+
+        let mut config = Config::parse(&content, &args)?;
+        config.args = args;
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    /// Reads the config file again, keeping the same command line overrides.
+    pub fn reload(&self) -> Result<Config, String> {
+        Config::load(self.args.clone())
+    }
+
+    /// Parses the file as TOML, then again with the overrides from `args` on top, so
+    /// an error is blamed on the file only if the file alone has it.
+    fn parse(content: &str, args: &[String]) -> Result<Config, String> {
+        let file_error = |err: toml::de::Error| format!("{}: {}", CONFIG_FILE_NAME, err);
+        let config = toml::from_str(content).map_err(file_error)?;
+        if args.is_empty() {
+            return Ok(config);
+        }
+
+        let mut settings: Table = content.parse().map_err(file_error)?;
+        override_settings(&mut settings, args)?;
+        // Going through text again keeps the setting at fault in the error message.
+        let merged = toml::to_string(&settings).map_err(|err| err.to_string())?;
+        toml::from_str(&merged).map_err(|err| format!("command line: {}", err))
+    }
+
+    /// The seed for the next run: the configured one, or a fresh random one.
+    pub fn run_seed(&self) -> u64 {
+        match self.seed {
+            Some(seed) => seed,
+            None => u64::from(thread_rng().gen::<u32>()),
+        }
+    }
+
+    /// Checks settings that only make sense together.
+    fn validate(&self) -> Result<(), String> {
+        let tuning = &self.tuning;
+        if tuning.ground_y >= tuning.screen_height as f32 {
+            return Err(format!(
+                "ground_y ({}) must be above the bottom of the screen ({})",
+                tuning.ground_y, tuning.screen_height
+            ));
+        }
+        // Keyframes replace the base pipe gap, so they have to keep to the same limits.
+        let gaps = iter::once(("pipe_gap".to_string(), tuning.pipe_gap)).chain(
+            tuning.difficulty.keyframes.iter().map(|keyframe| {
+                (
+                    format!("the pipe_gap of the keyframe at {}", keyframe.at),
+                    keyframe.difficulty.pipe_gap,
+                )
+            }),
+        );
+        for (name, gap) in gaps {
+            if gap < BIRD_HEIGHT {
+                return Err(format!(
+                    "{} ({}) must be at least the height of the bird ({})",
+                    name, gap, BIRD_HEIGHT
+                ));
+            }
+            if tuning.pipe_motion.gap_swing >= gap {
+                return Err(format!(
+                    "the gap swing of pipe_motion ({}) must be less than {} ({})",
+                    tuning.pipe_motion.gap_swing, name, gap
+                ));
+            }
+        }
+        if storage::ghost_path(&self.highscore_file) == Path::new(&self.highscore_file) {
+            return Err(format!(
+                "highscore_file ({}) can't end in .ghost, which is where its ghost is kept",
+                self.highscore_file
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Puts each `--key value` pair from the command line into `settings`, where a key in
+/// a table is written with dots like `tuning.gravity`. Values are read as TOML, and
+/// anything that isn't valid TOML on its own, like a bare file name, as a string.
+fn override_settings(settings: &mut Table, args: &[String]) -> Result<(), String> {
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let path = match arg.strip_prefix("--") {
+            Some(key) => key,
+            None => return Err(format!("unexpected argument `{}`", arg)),
+        };
+        let value = match args.next() {
+            Some(value) => value,
+            None => return Err(format!("missing value for `{}`", arg)),
+        };
+
+        let value = format!("value = {}", value)
+            .parse::<Table>()
+            .ok()
+            .and_then(|mut table| table.remove("value"))
+            .unwrap_or_else(|| Value::String(value.clone()));
+
+        let mut keys: Vec<&str> = path.split('.').collect();
+        let key = keys.pop().unwrap_or_default();
+        let mut table = &mut *settings;
+        for name in keys {
+            let entry = table
+                .entry(name.to_string())
+                .or_insert_with(|| Value::Table(Table::new()));
+            table = match entry {
+                Value::Table(table) => table,
+                _ => return Err(format!("`{}` is not a table in `{}`", name, arg)),
+            };
+        }
+        table.insert(key.to_string(), value);
+    }
+
+    Ok(())
+}
+
+/// Reads a setting that has to be greater than zero.
+pub fn positive<'de, D>(deserializer: D) -> Result<f32, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match f32::deserialize(deserializer)? {
+        number if number > 0.0 && number.is_finite() => Ok(number),
+        number => Err(D::Error::custom(format!("{} is not positive", number))),
+    }
+}
+
+/// Reads a screen dimension, in whole pixels.
+pub fn size<'de, D>(deserializer: D) -> Result<i32, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match i32::deserialize(deserializer)? {
+        size if size > 0 => Ok(size),
+        size => Err(D::Error::custom(format!("{} is not a positive size", size))),
+    }
+}
+
+fn duration<'de, D>(deserializer: D) -> Result<f32, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match f32::deserialize(deserializer)? {
+        duration if duration >= 0.0 && duration.is_finite() => Ok(duration),
+        duration => Err(D::Error::custom(format!(
+            "{} is not a transition duration",
+            duration
+        ))),
+    }
+}
+
+fn file_name<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match String::deserialize(deserializer)? {
+        path if path.is_empty() => Err(D::Error::custom("the highscore file can't be empty")),
+        path => Ok(path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::prefabs::bird::Hitbox;
+    use crate::systems::difficulty::Progress;
+    use crate::systems::gamemode::GameMode;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn leaves_out_settings_at_their_defaults() {
+        let config =
+            Config::parse("[tuning]\ngravity = 900\nbird_hitbox = \"circle\"", &[]).unwrap();
+
+        assert_eq!(config.tuning.gravity, 900.0);
+        assert_eq!(config.tuning.bird_hitbox, Hitbox::Circle);
+        assert_eq!(config.tuning.flap_speed, Tuning::default().flap_speed);
+        assert_eq!(config.highscore_file, FILE_NAME);
+        assert_eq!(Config::parse("", &[]).unwrap().tuning, Tuning::default());
+    }
+
+    #[test]
+    fn keeps_hashes_inside_quoted_values() {
+        let config = Config::parse("highscore_file = \"scores#1.txt\" # best so far", &[]).unwrap();
+
+        assert_eq!(config.highscore_file, "scores#1.txt");
+    }
+
+    #[test]
+    fn reads_nested_settings() {
+        let content = "[tuning]
+pipe_motion = [10, 2, 0, 1]
+mode = \"tunnel\"
+
+[tuning.difficulty]
+by = \"time\"
+keyframes = [
+    [0, 1.3, 120, 100, 180],
+    [30, 1, 100, 100, 200],
+]";
+        let config = Config::parse(content, &[]).unwrap();
+
+        assert_eq!(config.tuning.difficulty.progress, Progress::Time);
+        assert_eq!(config.tuning.difficulty.keyframes.len(), 2);
+        assert_eq!(config.tuning.pipe_motion.amplitude, 10.0);
+        assert_eq!(config.tuning.mode, GameMode::Tunnel);
+    }
+
+    #[test]
+    fn command_line_overrides_the_file() {
+        let config = Config::parse(
+            "seed = 7\n[tuning]\ngravity = 900\nmode = \"tunnel\"",
+            &args(&[
+                "--tuning.gravity",
+                "800",
+                "--highscore_file",
+                "mine.txt",
+                "--tuning.difficulty.by",
+                "time",
+            ]),
+        )
+        .unwrap();
+
+        assert_eq!(config.seed, Some(7));
+        assert_eq!(config.tuning.gravity, 800.0);
+        assert_eq!(config.tuning.mode, GameMode::Tunnel);
+        assert_eq!(config.tuning.difficulty.progress, Progress::Time);
+        assert_eq!(config.highscore_file, "mine.txt");
+    }
+
+    #[test]
+    fn rejects_bad_settings() {
+        for content in [
+            "gravity = 900",
+            "highscore_file = \"\"",
+            "transition_duration = -1",
+            "[tuning]\ngravity = 0",
+            "[tuning]\ngravity = \"fast\"",
+            "[tuning]\nscreen_width = -1",
+            "[tuning]\nbird_hitbox = \"square\"",
+            "[tuning]\nmode = \"maze\"",
+            "[tuning]\npipe_motion = [0, 0, 0, 1]",
+            "[tuning]\npipe_motion = [0, 1, 0, 1, 1]",
+            "[tuning.difficulty]\nby = \"distance\"",
+            "seed 1234",
+        ] {
+            let err = Config::parse(content, &[]).unwrap_err();
+            assert!(err.starts_with(CONFIG_FILE_NAME), "{}", err);
+        }
+    }
+
+    #[test]
+    fn rejects_pipe_gaps_the_bird_cant_pass() {
+        for content in [
+            "[tuning]\npipe_gap = 20",
+            "[tuning]\npipe_motion = [0, 1, 120, 1]",
+            "[tuning.difficulty]\nkeyframes = [[0, 1, 120, 100, 180], [10, 1, 20, 100, 180]]",
+            "[tuning]\npipe_motion = [0, 1, 50, 1]\n\
+             [tuning.difficulty]\nkeyframes = [[0, 1, 120, 100, 180], [10, 1, 50, 100, 180]]",
+        ] {
+            let config = Config::parse(content, &[]).unwrap();
+            assert!(config.validate().is_err(), "{}", content);
+        }
+
+        let content = "[tuning]\npipe_motion = [0, 1, 50, 1]\n\
+                       [tuning.difficulty]\nkeyframes = [[0, 1, 120, 100, 180], [10, 1, 60, 100, 180]]";
+        assert_eq!(Config::parse(content, &[]).unwrap().validate(), Ok(()));
+    }
+
+    #[test]
+    fn rejects_bad_arguments() {
+        for arg in [
+            &["--gravity", "900"][..],
+            &["--tuning.gravity", "none"],
+            &["--seed.tuning", "1"],
+            &["--seed"],
+            &["seed", "1"],
+        ] {
+            assert!(Config::parse("seed = 1", &args(arg)).is_err(), "{:?}", arg);
+        }
+    }
+}
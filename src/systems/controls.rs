@@ -0,0 +1,78 @@
+use tetra::input::{self, MouseButton};
+use tetra::Context;
+
+use crate::systems::simulation::{Input, Simulation};
+
+/// Where the actions fed into the simulation come from.
+pub trait InputSource {
+    /// Called once per rendered frame, before any simulation steps run.
+    fn update(&mut self, _ctx: &Context) {}
+
+    /// Called when the game carries on after a pause, to drop any input from before.
+    fn resume(&mut self) {}
+
+    /// Called once per simulation step, before `simulation` takes it.
+    fn next_input(&mut self, simulation: &Simulation) -> Input;
+}
+
+/// Flaps once for every press of the left mouse button.
+pub struct MouseInput {
+    is_mouse_down: bool,
+    pending: Input,
+}
+
+impl MouseInput {
+    pub fn new() -> MouseInput {
+        // The click that started the scene is still held down.
+        MouseInput {
+            is_mouse_down: true,
+            pending: Input::None,
+        }
+    }
+}
+
+impl InputSource for MouseInput {
+    fn update(&mut self, ctx: &Context) {
+        if input::is_mouse_button_down(ctx, MouseButton::Left) {
+            if !self.is_mouse_down {
+                self.is_mouse_down = true;
+                self.pending = Input::Flap;
+            }
+        } else {
+            self.is_mouse_down = false;
+        }
+    }
+
+    fn resume(&mut self) {
+        // The click that closed the pause menu may still be held down.
+        *self = MouseInput::new();
+    }
+
+    fn next_input(&mut self, _simulation: &Simulation) -> Input {
+        std::mem::replace(&mut self.pending, Input::None)
+    }
+}
+
+/// Plays back the inputs of a recorded run, step by step.
+pub struct ReplayInput {
+    inputs: Vec<(u32, Input)>,
+    index: usize,
+}
+
+impl ReplayInput {
+    pub fn new(inputs: Vec<(u32, Input)>) -> ReplayInput {
+        ReplayInput { inputs, index: 0 }
+    }
+}
+
+impl InputSource for ReplayInput {
+    fn next_input(&mut self, simulation: &Simulation) -> Input {
+        match self.inputs.get(self.index) {
+            Some(&(recorded_frame, input)) if recorded_frame == simulation.frame() => {
+                self.index += 1;
+                input
+            }
+            _ => Input::None,
+        }
+    }
+}
@@ -0,0 +1,261 @@
+use serde::de::{Deserializer, Error};
+use serde::Deserialize;
+
+/// What the difficulty curve advances with.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Progress {
+    /// Points scored in the run.
+    Score,
+    /// Seconds since the first flap.
+    Time,
+}
+
+/// The settings the difficulty curve controls.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Difficulty {
+    /// Time between two pipe groups, in seconds.
+    pub spawn_interval: f32,
+    /// Vertical space between the top and the bottom pipe, in pixels.
+    pub pipe_gap: f32,
+    /// How far the gap may be placed above or below its usual height, in pixels.
+    pub gap_offset_range: f32,
+    /// Speed of the pipes and the ground, in pixels per second.
+    pub scroll_speed: f32,
+}
+
+impl Difficulty {
+    fn lerp(from: Difficulty, to: Difficulty, t: f32) -> Difficulty {
+        let lerp = |from: f32, to: f32| from + (to - from) * t;
+        Difficulty {
+            spawn_interval: lerp(from.spawn_interval, to.spawn_interval),
+            pipe_gap: lerp(from.pipe_gap, to.pipe_gap),
+            gap_offset_range: lerp(from.gap_offset_range, to.gap_offset_range),
+            scroll_speed: lerp(from.scroll_speed, to.scroll_speed),
+        }
+    }
+}
+
+/// The difficulty reached once progress gets to `at`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Keyframe {
+    pub at: f32,
+    pub difficulty: Difficulty,
+}
+
+/// A piecewise linear curve through keyframes sorted by `at`. Before the first keyframe
+/// the difficulty stays at the first one, and after the last it stays at the last.
+/// Without keyframes the game keeps the base settings from the tuning throughout.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DifficultyCurve {
+    #[serde(rename = "by")]
+    pub progress: Progress,
+    #[serde(deserialize_with = "deserialize_keyframes")]
+    pub keyframes: Vec<Keyframe>,
+}
+
+impl Default for DifficultyCurve {
+    fn default() -> DifficultyCurve {
+        DifficultyCurve {
+            progress: Progress::Score,
+            keyframes: Vec::new(),
+        }
+    }
+}
+
+impl DifficultyCurve {
+    /// The difficulty at `progress`, or `base` if the curve is empty.
+    pub fn at(&self, progress: f32, base: Difficulty) -> Difficulty {
+        let next = self
+            .keyframes
+            .iter()
+            .position(|keyframe| keyframe.at > progress);
+
+        match next {
+            None => self.keyframes.last().map_or(base, |last| last.difficulty),
+            Some(0) => self.keyframes[0].difficulty,
+            Some(index) => {
+                let from = self.keyframes[index - 1];
+                let to = self.keyframes[index];
+                let t = (progress - from.at) / (to.at - from.at);
+                Difficulty::lerp(from.difficulty, to.difficulty, t)
+            }
+        }
+    }
+
+    /// How many keyframes have been reached at `progress`, starting at level 1.
+    pub fn level(&self, progress: f32) -> usize {
+        self.keyframes
+            .iter()
+            .filter(|keyframe| keyframe.at <= progress)
+            .count()
+            .max(1)
+    }
+}
+
+/// Reads keyframes written as `[at, spawn_interval, pipe_gap, gap_offset_range, scroll_speed]`
+/// rows, sorted by `at`.
+fn deserialize_keyframes<'de, D>(deserializer: D) -> Result<Vec<Keyframe>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let mut keyframes: Vec<Keyframe> = Vec::new();
+    for row in Vec::<Vec<f32>>::deserialize(deserializer)? {
+        let (at, values) = match row.as_slice() {
+            [at, values @ ..] if values.len() == 4 => (*at, values),
+            _ => {
+                return Err(D::Error::custom(format!(
+                    "{:?} should be [at, spawn_interval, pipe_gap, gap_offset_range, scroll_speed]",
+                    row
+                )))
+            }
+        };
+        if !at.is_finite()
+            || values
+                .iter()
+                .any(|value| !(*value > 0.0 && value.is_finite()))
+        {
+            return Err(D::Error::custom(format!(
+                "keyframe {:?} has a value that isn't positive",
+                row
+            )));
+        }
+        if let Some(previous) = keyframes.last() {
+            if at <= previous.at {
+                return Err(D::Error::custom(format!(
+                    "keyframe at {} must come after the one at {}",
+                    at, previous.at
+                )));
+            }
+        }
+
+        keyframes.push(Keyframe {
+            at,
+            difficulty: Difficulty {
+                spawn_interval: values[0],
+                pipe_gap: values[1],
+                gap_offset_range: values[2],
+                scroll_speed: values[3],
+            },
+        });
+    }
+
+    Ok(keyframes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn difficulty(spawn_interval: f32, pipe_gap: f32, scroll_speed: f32) -> Difficulty {
+        Difficulty {
+            spawn_interval,
+            pipe_gap,
+            gap_offset_range: 100.0,
+            scroll_speed,
+        }
+    }
+
+    fn parse_keyframes(value: &str) -> Result<Vec<Keyframe>, toml::de::Error> {
+        toml::from_str::<DifficultyCurve>(&format!("keyframes = {}", value))
+            .map(|curve| curve.keyframes)
+    }
+
+    fn curve(value: &str) -> DifficultyCurve {
+        DifficultyCurve {
+            progress: Progress::Score,
+            keyframes: parse_keyframes(value).unwrap(),
+        }
+    }
+
+    #[test]
+    fn parses_keyframes() {
+        let keyframes = parse_keyframes("[[0, 1.5, 120, 100, 180], [ 20,1,100,100,200 ]]").unwrap();
+
+        assert_eq!(
+            keyframes,
+            vec![
+                Keyframe {
+                    at: 0.0,
+                    difficulty: difficulty(1.5, 120.0, 180.0),
+                },
+                Keyframe {
+                    at: 20.0,
+                    difficulty: difficulty(1.0, 100.0, 200.0),
+                },
+            ]
+        );
+        assert_eq!(parse_keyframes("[]").unwrap(), Vec::new());
+    }
+
+    #[test]
+    fn interpolates_between_keyframes() {
+        let curve = curve("[[10, 2, 120, 100, 180], [20, 1, 100, 100, 200]]");
+        let base = difficulty(4.0, 200.0, 100.0);
+
+        assert_eq!(curve.at(15.0, base), difficulty(1.5, 110.0, 190.0));
+        assert_eq!(curve.at(17.5, base), difficulty(1.25, 105.0, 195.0));
+        assert_eq!(curve.at(10.0, base), difficulty(2.0, 120.0, 180.0));
+    }
+
+    #[test]
+    fn holds_the_first_keyframe_before_it() {
+        let curve = curve("[[10, 2, 120, 100, 180], [20, 1, 100, 100, 200]]");
+        let base = difficulty(4.0, 200.0, 100.0);
+
+        assert_eq!(curve.at(0.0, base), difficulty(2.0, 120.0, 180.0));
+        assert_eq!(curve.at(9.9, base), difficulty(2.0, 120.0, 180.0));
+    }
+
+    #[test]
+    fn holds_the_last_keyframe_after_it() {
+        let curve = curve("[[10, 2, 120, 100, 180], [20, 1, 100, 100, 200]]");
+        let base = difficulty(4.0, 200.0, 100.0);
+
+        assert_eq!(curve.at(20.0, base), difficulty(1.0, 100.0, 200.0));
+        assert_eq!(curve.at(1000.0, base), difficulty(1.0, 100.0, 200.0));
+    }
+
+    #[test]
+    fn keeps_the_base_without_keyframes() {
+        let base = difficulty(4.0, 200.0, 100.0);
+
+        assert_eq!(DifficultyCurve::default().at(0.0, base), base);
+        assert_eq!(DifficultyCurve::default().at(50.0, base), base);
+        assert_eq!(DifficultyCurve::default().level(50.0), 1);
+    }
+
+    #[test]
+    fn counts_the_keyframes_reached() {
+        let curve = curve("[[10, 2, 120, 100, 180], [20, 1, 100, 100, 200]]");
+
+        assert_eq!(curve.level(0.0), 1);
+        assert_eq!(curve.level(10.0), 1);
+        assert_eq!(curve.level(19.0), 1);
+        assert_eq!(curve.level(20.0), 2);
+    }
+
+    #[test]
+    fn rejects_unsorted_keyframes() {
+        assert!(parse_keyframes("[[20, 1, 100, 100, 200], [10, 2, 120, 100, 180]]").is_err());
+        assert!(parse_keyframes("[[10, 1, 100, 100, 200], [10, 2, 120, 100, 180]]").is_err());
+    }
+
+    #[test]
+    fn rejects_malformed_keyframes() {
+        for value in [
+            "",
+            "[0, 1, 100, 100, 200]",
+            "[[0, 1, 100, 100]]",
+            "[[0, 1, 100, 100, 200, 5]]",
+            "[[0, 1, 0, 100, 200]]",
+            "[[0, 1, -100, 100, 200]]",
+            "[[0, one, 100, 100, 200]]",
+            "[[0, 1, 100, 100, inf]]",
+            "[[0, 1, 100, 100, nan]]",
+        ] {
+            assert!(parse_keyframes(value).is_err(), "`{}`", value);
+        }
+    }
+}
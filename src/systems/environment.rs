@@ -0,0 +1,339 @@
+use std::io::{self, BufRead, Write};
+
+use tetra::math::Vec2;
+
+use crate::prefabs::pipes::GAP_CENTER;
+use crate::systems::simulation::{Event, Input, Simulation};
+use crate::systems::sprites::Sprites;
+use crate::systems::tuning::Tuning;
+
+/// Reward for every step the bird survives.
+const STEP_REWARD: f32 = 0.1;
+/// Reward for passing a pipe group.
+const SCORE_REWARD: f32 = 1.0;
+/// Reward for crashing.
+const CRASH_REWARD: f32 = -1.0;
+
+/// Where a gap is, seen from the bird.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GapObservation {
+    /// Pixels from the bird to the middle of the gap, across.
+    pub dx: f32,
+    /// Pixels from the bird down to the middle of the gap.
+    pub dy: f32,
+}
+
+/// What an agent gets to see of the game after each step.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Observation {
+    /// Pixels from the top of the screen.
+    pub bird_y: f32,
+    /// Pixels per second, positive downwards.
+    pub bird_velocity: f32,
+    /// The next two gaps the bird hasn't cleared. Gaps that haven't spawned yet are
+    /// reported at the usual height, at the right edge of the screen.
+    pub gaps: [GapObservation; 2],
+}
+
+impl Observation {
+    /// The observation as a flat list of numbers, in the order of the fields.
+    pub fn to_array(self) -> [f32; 6] {
+        [
+            self.bird_y,
+            self.bird_velocity,
+            self.gaps[0].dx,
+            self.gaps[0].dy,
+            self.gaps[1].dx,
+            self.gaps[1].dy,
+        ]
+    }
+}
+
+/// The game as a gym-style environment for training agents: the same `Simulation` the
+/// game runs, without a window, stepped by whoever drives it.
+pub struct Environment {
+    simulation: Simulation,
+}
+
+impl Environment {
+    pub fn new(tuning: Tuning, sprites: &Sprites) -> Environment {
+        Environment {
+            simulation: Simulation::new(0, tuning, sprites),
+        }
+    }
+
+    /// Starts a new run with the pipes for `seed`. Unlike in the game, the run starts
+    /// straight away, without waiting for a flap.
+    pub fn reset(&mut self, seed: u64) -> Observation {
+        self.simulation.reset(seed);
+        self.simulation.start();
+        self.observe()
+    }
+
+    /// Takes `action` for one simulation step, and returns what can be seen after it,
+    /// the reward for it and whether the run is over.
+    pub fn step(&mut self, action: Input) -> (Observation, f32, bool) {
+        if self.is_done() {
+            return (self.observe(), 0.0, true);
+        }
+
+        let mut reward = STEP_REWARD;
+        for event in self.simulation.step(action) {
+            match event {
+                Event::Score => reward += SCORE_REWARD,
+                Event::PipeHit | Event::GroundHit => reward = CRASH_REWARD,
+                Event::Flap | Event::Finish => {}
+            }
+        }
+
+        (self.observe(), reward, self.is_done())
+    }
+
+    /// A run is over as soon as the bird crashes, without waiting for it to fall.
+    fn is_done(&self) -> bool {
+        !self.simulation.bird.alive || self.simulation.game_over
+    }
+
+    fn observe(&self) -> Observation {
+        let bird = &self.simulation.bird;
+        let spawn_x = self.simulation.tuning().screen_width as f32;
+        let mut gaps = self
+            .simulation
+            .upcoming_pipes()
+            .into_iter()
+            .map(|pipe_group| pipe_group.gap_center())
+            .chain(std::iter::repeat(Vec2::new(spawn_x, GAP_CENTER)))
+            .map(|center| GapObservation {
+                dx: center.x - bird.position.x,
+                dy: center.y - bird.position.y,
+            });
+
+        Observation {
+            bird_y: bird.position.y,
+            bird_velocity: bird.velocity().y,
+            gaps: [gaps.next().unwrap(), gaps.next().unwrap()],
+        }
+    }
+}
+
+/// Drives `environment` from text commands, one per line of `input`, so agents can be
+/// trained from any language. `reset SEED` starts a run and `step 0` or `step 1` waits
+/// or flaps. Each answers with a line of the six observed numbers, followed for `step`
+/// by the reward and 1 if the run is over or 0 if not.
+pub fn serve(
+    mut environment: Environment,
+    input: impl BufRead,
+    mut output: impl Write,
+) -> io::Result<()> {
+    let mut started = false;
+
+    for line in input.lines() {
+        let line = line?;
+        let mut words = line.split_whitespace();
+        let reply = match (words.next(), words.next(), words.next()) {
+            (Some("reset"), Some(seed), None) => match seed.parse() {
+                Ok(seed) => {
+                    started = true;
+                    Ok(format_numbers(&environment.reset(seed).to_array()))
+                }
+                Err(_) => Err(format!("`{}` is not a valid seed", seed)),
+            },
+            (Some("step"), Some(action), None) if started => {
+                let action = match action {
+                    "0" => Ok(Input::None),
+                    "1" => Ok(Input::Flap),
+                    _ => Err(format!("`{}` is not an action, expected 0 or 1", action)),
+                };
+                action.map(|action| {
+                    let (observation, reward, done) = environment.step(action);
+                    format!(
+                        "{} {} {}",
+                        format_numbers(&observation.to_array()),
+                        reward,
+                        done as u8
+                    )
+                })
+            }
+            (Some("step"), Some(_), None) => Err("`reset` must come first".to_string()),
+            (None, _, _) => continue,
+            _ => Err(format!(
+                "expected `reset SEED` or `step ACTION`, got `{}`",
+                line.trim()
+            )),
+        };
+
+        match reply {
+            Ok(reply) => writeln!(output, "{}", reply)?,
+            Err(err) => writeln!(output, "error: {}", err)?,
+        }
+        output.flush()?;
+    }
+
+    Ok(())
+}
+
+fn format_numbers(numbers: &[f32]) -> String {
+    numbers
+        .iter()
+        .map(|number| number.to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::prefabs::bird::BIRD_X;
+    use crate::systems::autopilot::Autopilot;
+    use crate::systems::controls::InputSource;
+
+    fn environment() -> Environment {
+        Environment::new(Tuning::default(), &Sprites::solid())
+    }
+
+    /// Serves `input` and returns the lines written back.
+    fn serve_lines(input: &str) -> Vec<String> {
+        let mut output = Vec::new();
+        serve(environment(), input.as_bytes(), &mut output).unwrap();
+        String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(str::to_string)
+            .collect()
+    }
+
+    fn numbers(line: &str) -> Vec<f32> {
+        line.split(' ')
+            .map(|number| number.parse().unwrap())
+            .collect()
+    }
+
+    /// Lets the autopilot fly, and returns the actions it took until the first point.
+    fn actions_to_score(environment: &mut Environment, seed: u64) -> Vec<Input> {
+        let mut autopilot = Autopilot::new();
+        let mut actions = Vec::new();
+        environment.reset(seed);
+
+        loop {
+            let action = autopilot.next_input(&environment.simulation);
+            let (_, reward, done) = environment.step(action);
+            actions.push(action);
+            assert!(!done, "crashed before scoring");
+            if reward > STEP_REWARD {
+                return actions;
+            }
+        }
+    }
+
+    #[test]
+    fn reset_observes_the_bird_and_the_gaps_to_come() {
+        let tuning = Tuning::default();
+        let observation = environment().reset(3);
+        let bird_y = tuning.screen_height as f32 / 2.0;
+        let next_gap = GapObservation {
+            dx: tuning.screen_width as f32 - BIRD_X,
+            dy: GAP_CENTER - bird_y,
+        };
+
+        assert_eq!(
+            observation,
+            Observation {
+                bird_y,
+                bird_velocity: 0.0,
+                gaps: [next_gap, next_gap],
+            }
+        );
+        assert_eq!(
+            observation.to_array(),
+            [
+                bird_y,
+                0.0,
+                next_gap.dx,
+                next_gap.dy,
+                next_gap.dx,
+                next_gap.dy
+            ]
+        );
+    }
+
+    #[test]
+    fn serves_observations_in_field_order() {
+        let lines = serve_lines("reset 3\nstep 1\n");
+        let mut environment = environment();
+
+        assert_eq!(lines.len(), 2);
+        assert_eq!(numbers(&lines[0]), environment.reset(3).to_array().to_vec());
+
+        let (observation, reward, done) = environment.step(Input::Flap);
+        let mut expected = observation.to_array().to_vec();
+        expected.extend([reward, done as u8 as f32]);
+        assert_eq!(numbers(&lines[1]), expected);
+        assert!(observation.bird_velocity < 0.0);
+        assert_eq!(reward, STEP_REWARD);
+    }
+
+    #[test]
+    fn rewards_scoring() {
+        let actions = actions_to_score(&mut environment(), 3);
+        let input: String = std::iter::once("reset 3".to_string())
+            .chain(actions.iter().map(|action| match action {
+                Input::Flap => "step 1".to_string(),
+                Input::None => "step 0".to_string(),
+            }))
+            .map(|line| line + "\n")
+            .collect();
+
+        let lines = serve_lines(&input);
+        let last = numbers(lines.last().unwrap());
+        assert_eq!(last[6], STEP_REWARD + SCORE_REWARD);
+        assert_eq!(last[7], 0.0);
+        for line in &lines[1..lines.len() - 1] {
+            assert_eq!(numbers(line)[6], STEP_REWARD);
+        }
+    }
+
+    #[test]
+    fn punishes_crashing_and_ends_the_run() {
+        let mut environment = environment();
+        environment.reset(3);
+
+        let mut steps = 0;
+        let (_, reward, done) = loop {
+            steps += 1;
+            let (observation, reward, done) = environment.step(Input::None);
+            if done {
+                break (observation, reward, done);
+            }
+            assert_eq!(reward, STEP_REWARD);
+            assert!(steps < 1000, "never crashed");
+        };
+        assert!(done);
+        assert_eq!(reward, CRASH_REWARD);
+
+        let (_, reward, done) = environment.step(Input::Flap);
+        assert_eq!((reward, done), (0.0, true));
+    }
+
+    #[test]
+    fn answers_malformed_lines_with_errors_and_carries_on() {
+        let lines = serve_lines(
+            "step 0\n\nreset\nreset -1\nreset 1 2\njump\nreset 1\nstep 2\nstep\nstep 0\n",
+        );
+
+        assert_eq!(lines.len(), 9, "{:?}", lines);
+        for (index, line) in lines.iter().enumerate() {
+            let is_error = line.starts_with("error: ");
+            match index {
+                5 | 8 => assert!(!is_error, "{}", line),
+                _ => assert!(is_error, "{}", line),
+            }
+        }
+        assert_eq!(lines[0], "error: `reset` must come first");
+        assert_eq!(
+            lines[3],
+            "error: expected `reset SEED` or `step ACTION`, got `reset 1 2`"
+        );
+        assert_eq!(lines[6], "error: `2` is not an action, expected 0 or 1");
+    }
+}
@@ -0,0 +1,42 @@
+use serde::de::{Deserializer, Error};
+use serde::Deserialize;
+
+use crate::systems::pipegenerators::{
+    PipeGenerator, Sequence, SineTunnel, Squeeze, Staircase, Uniform,
+};
+
+/// Which pipe layout a run is played with.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GameMode {
+    /// Every gap at a random height, like the original game.
+    Classic,
+    /// Gaps following a sine wave.
+    Tunnel,
+    /// Gaps climbing and descending in steps.
+    Stairs,
+    /// Random gaps with sections of narrower ones.
+    Squeeze,
+    /// Gaps read from a file, configured as `{ sequence = "path" }`.
+    #[serde(deserialize_with = "deserialize_sequence")]
+    Sequence(Sequence),
+}
+
+impl GameMode {
+    pub fn pipe_generator(&self, seed: u64) -> Box<dyn PipeGenerator> {
+        match self {
+            GameMode::Classic => Box::new(Uniform::new(seed)),
+            GameMode::Tunnel => Box::new(SineTunnel::new(seed)),
+            GameMode::Stairs => Box::new(Staircase::new(seed)),
+            GameMode::Squeeze => Box::new(Squeeze::new(seed)),
+            GameMode::Sequence(sequence) => Box::new(sequence.clone()),
+        }
+    }
+}
+
+fn deserialize_sequence<'de, D>(deserializer: D) -> Result<Sequence, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Sequence::load(&String::deserialize(deserializer)?).map_err(D::Error::custom)
+}
@@ -0,0 +1,77 @@
+use std::fs;
+use std::time::SystemTime;
+
+use crate::systems::config::Config;
+use crate::systems::tuning::Tuning;
+use crate::CONFIG_FILE_NAME;
+
+/// How often the config file is checked for changes, in seconds.
+const POLL_INTERVAL: f32 = 0.5;
+
+/// Watches the config file by polling its modification time, so tuning can be
+/// edited while the game runs.
+pub struct ConfigWatcher {
+    modified: Option<SystemTime>,
+    elapsed: f32,
+}
+
+impl ConfigWatcher {
+    pub fn new() -> ConfigWatcher {
+        ConfigWatcher {
+            modified: modified_time(),
+            elapsed: 0.0,
+        }
+    }
+
+    /// Reloads `config` if the file changed since the last poll. Returns `None` when
+    /// nothing changed and the parse error when the new content is invalid.
+    pub fn poll(&mut self, dt: f32, config: &Config) -> Option<Result<Config, String>> {
+        self.elapsed += dt;
+        if self.elapsed < POLL_INTERVAL {
+            return None;
+        }
+        self.elapsed = 0.0;
+
+        let modified = modified_time();
+        if modified == self.modified {
+            return None;
+        }
+        self.modified = modified;
+
+        Some(config.reload())
+    }
+}
+
+fn modified_time() -> Option<SystemTime> {
+    fs::metadata(CONFIG_FILE_NAME)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+/// Describes the settings `Simulation::retune` would change, one line each. Changes
+/// the difficulty keyframes of `old` override are reported as ignored.
+pub fn live_changes(old: &Tuning, new: &Tuning) -> Vec<String> {
+    let curved = !old.difficulty.keyframes.is_empty();
+
+    [
+        ("gravity", old.gravity, new.gravity, false),
+        ("flap_speed", old.flap_speed, new.flap_speed, false),
+        ("scroll_speed", old.scroll_speed, new.scroll_speed, curved),
+        (
+            "spawn_interval",
+            old.spawn_interval,
+            new.spawn_interval,
+            curved,
+        ),
+    ]
+    .iter()
+    .filter(|(_, old, new, _)| old != new)
+    .map(|(name, old, new, overridden)| {
+        if *overridden {
+            format!("{} {} -> {}\nignored, difficulty sets it", name, old, new)
+        } else {
+            format!("{} {} -> {}", name, old, new)
+        }
+    })
+    .collect()
+}
@@ -0,0 +1,424 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::systems::pipegenerators::Gap;
+use crate::LEVELS_DIRECTORY;
+
+/// Pixels between the last pipe group and the finish line unless a level says otherwise.
+const DEFAULT_FINISH: f32 = 200.0;
+
+/// A pipe group of a level, `distance` pixels after the previous one.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LevelPipe {
+    pub distance: f32,
+    pub gap: Gap,
+}
+
+/// A finite course of hand-placed pipes ending in a finish line.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Level {
+    /// The file name without extension, which progress is saved under.
+    pub id: String,
+    pub name: String,
+    pub pipes: Vec<LevelPipe>,
+    /// Pixels between the last pipe group and the finish line.
+    pub finish: f32,
+    /// The most flaps for three and for two stars. Any finish earns one.
+    pub star_flaps: Option<(u32, u32)>,
+}
+
+impl Level {
+    /// A level without pipe groups, to be filled in with the editor.
+    pub fn empty(id: String) -> Level {
+        Level {
+            id,
+            name: "Untitled".to_string(),
+            pipes: Vec::new(),
+            finish: DEFAULT_FINISH,
+            star_flaps: None,
+        }
+    }
+
+    /// Reads every `.level` file in `LEVELS_DIRECTORY`, in the order of their names.
+    pub fn campaign() -> Result<Vec<Level>, String> {
+        let entries = fs::read_dir(LEVELS_DIRECTORY)
+            .map_err(|err| format!("can't read `{}`: {}", LEVELS_DIRECTORY, err))?;
+
+        let mut paths: Vec<_> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|extension| extension == "level")
+            })
+            .collect();
+        paths.sort();
+
+        paths.iter().map(|path| Level::load(path)).collect()
+    }
+
+    pub fn load(path: &Path) -> Result<Level, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|err| format!("can't read `{}`: {}", path.display(), err))?;
+        let id = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        Level::parse(id, &path.display().to_string(), &content)
+    }
+
+    /// Parses a level with `key = value` lines for `name`, `finish` and `stars`, and
+    /// one `distance offset [height [amplitude period gap_swing speed]]` line per pipe
+    /// group. `#` starts a comment unless it is in a quoted name. Errors name `source`
+    /// and the line at fault.
+    pub fn parse(id: String, source: &str, content: &str) -> Result<Level, String> {
+        let mut level = Level {
+            name: id.clone(),
+            id,
+            pipes: Vec::new(),
+            finish: DEFAULT_FINISH,
+            star_flaps: None,
+        };
+
+        for (index, line) in content.lines().enumerate() {
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+            let error = |message: String| format!("{}:{}: {}", source, index + 1, message);
+
+            match line.split_once('=') {
+                Some((key, value)) => level.set(key.trim(), value.trim()).map_err(error)?,
+                None => {
+                    let (distance, gap) =
+                        line.split_once(char::is_whitespace).ok_or_else(|| {
+                            error("expected `distance offset` for a pipe group".to_string())
+                        })?;
+                    let distance = match distance.parse::<f32>() {
+                        Ok(distance) if distance > 0.0 && distance.is_finite() => distance,
+                        _ => {
+                            return Err(error(format!("`{}` is not a positive distance", distance)))
+                        }
+                    };
+                    let gap = Gap::parse(gap).map_err(error)?;
+
+                    level.pipes.push(LevelPipe { distance, gap });
+                }
+            }
+        }
+
+        if level.pipes.is_empty() {
+            return Err(format!("{}: no pipe groups in the level", source));
+        }
+
+        Ok(level)
+    }
+
+    fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "name" => self.name = parse_name(value)?,
+            "finish" => {
+                self.finish = match value.parse::<f32>() {
+                    Ok(finish) if finish >= 0.0 && finish.is_finite() => finish,
+                    _ => return Err(format!("`{}` is not a distance to the finish", value)),
+                }
+            }
+            "stars" => {
+                let flaps: Vec<u32> = value
+                    .split_whitespace()
+                    .map(|flaps| flaps.parse().ok())
+                    .collect::<Option<_>>()
+                    .unwrap_or_default();
+                self.star_flaps = match flaps.as_slice() {
+                    &[three, two] if three <= two => Some((three, two)),
+                    _ => {
+                        return Err(format!(
+                            "`{}` should be the most flaps for three and for two stars",
+                            value
+                        ))
+                    }
+                }
+            }
+            _ => return Err(format!("unknown setting `{}`", key)),
+        }
+
+        Ok(())
+    }
+
+    /// Where the level is saved, in `LEVELS_DIRECTORY` under its id.
+    pub fn path(&self) -> PathBuf {
+        Path::new(LEVELS_DIRECTORY).join(format!("{}.level", self.id))
+    }
+
+    /// Writes the level to its `path`.
+    pub fn save(&self) -> io::Result<()> {
+        fs::write(self.path(), self.contents())
+    }
+
+    /// The level in the format `Level::parse` reads.
+    fn contents(&self) -> String {
+        let name = self.name.replace('\\', "\\\\").replace('"', "\\\"");
+        let mut content = format!("name = \"{}\"\n", name);
+        if let Some((three, two)) = self.star_flaps {
+            content += &format!("stars = {} {}\n", three, two);
+        }
+        content += &format!("finish = {}\n", self.finish);
+        content += "\n# distance offset [height [amplitude period gap_swing speed]]\n";
+        for pipe in &self.pipes {
+            content += &format!("{} {}\n", pipe.distance, pipe.gap);
+        }
+
+        content
+    }
+
+    /// The stars earned by finishing with `flaps` flaps.
+    pub fn stars(&self, flaps: u32) -> u32 {
+        match self.star_flaps {
+            Some((three, _)) if flaps <= three => 3,
+            Some((_, two)) if flaps <= two => 2,
+            Some(_) => 1,
+            None => 3,
+        }
+    }
+}
+
+/// Cuts a `#` comment off `line`, unless the `#` is inside double quotes.
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    let mut escaped = false;
+    for (index, c) in line.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            '#' if !quoted => return &line[..index],
+            _ => {}
+        }
+    }
+
+    line
+}
+
+/// Reads a name in double quotes, where `\"` and `\\` stand for `"` and `\`. A name
+/// without quotes is taken as written.
+fn parse_name(value: &str) -> Result<String, String> {
+    let mut chars = match value.strip_prefix('"') {
+        Some(quoted) => quoted.chars(),
+        None => return Ok(value.to_string()),
+    };
+
+    let mut name = String::new();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(c @ ('"' | '\\')) => name.push(c),
+                _ => return Err(format!("`{}` has an escape other than \\\" or \\\\", value)),
+            },
+            '"' if chars.as_str().is_empty() => return Ok(name),
+            '"' => return Err(format!("`{}` goes on after its closing quote", value)),
+            c => name.push(c),
+        }
+    }
+
+    Err(format!("`{}` is missing its closing quote", value))
+}
+
+/// How far a run has got through a level.
+#[derive(Clone, Debug)]
+pub struct Course {
+    pub level: Level,
+    /// Pixels into the level the run starts at, e.g. to play-test from the middle.
+    start: f32,
+    next: usize,
+    /// Pixels scrolled since the first flap, and before the last update.
+    scrolled: f32,
+    previous_scrolled: f32,
+    /// Where the next pipe group is due, and the finish line, in scrolled pixels.
+    next_at: f32,
+    finish_at: f32,
+}
+
+impl Course {
+    /// Starts `level` as if `start` pixels of it had already scrolled by. Pipe groups
+    /// from before then are due straight away, already moved into place. A level
+    /// without pipe groups goes straight to the finish line.
+    pub fn new(level: Level, start: f32) -> Course {
+        let finish_at = level.pipes.iter().map(|pipe| pipe.distance).sum::<f32>() + level.finish;
+        let next_at = level.pipes.first().map_or(0.0, |pipe| pipe.distance);
+
+        Course {
+            level,
+            start,
+            next: 0,
+            scrolled: start,
+            previous_scrolled: start,
+            next_at,
+            finish_at,
+        }
+    }
+
+    pub fn restart(&mut self) {
+        *self = Course::new(self.level.clone(), self.start);
+    }
+
+    pub fn advance(&mut self, distance: f32) {
+        self.previous_scrolled = self.scrolled;
+        self.scrolled += distance;
+    }
+
+    /// The next pipe group if it is due, with how many pixels it is overdue by.
+    pub fn due_pipe(&mut self) -> Option<(Gap, f32)> {
+        let pipe = self.level.pipes.get(self.next)?;
+        if self.scrolled < self.next_at {
+            return None;
+        }
+
+        let overdue = self.scrolled - self.next_at;
+        self.next += 1;
+        if let Some(next) = self.level.pipes.get(self.next) {
+            self.next_at += next.distance;
+        }
+
+        Some((pipe.gap, overdue))
+    }
+
+    /// Where the finish line is on a screen `screen_width` pixels wide, `blend` of the
+    /// way through the last update.
+    pub fn finish_x(&self, screen_width: f32, blend: f32) -> f32 {
+        let scrolled = self.previous_scrolled + (self.scrolled - self.previous_scrolled) * blend;
+        screen_width + self.finish_at - scrolled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::prefabs::pipes::PipeMotion;
+
+    fn gap(offset: f32, height: Option<f32>, motion: Option<PipeMotion>) -> Gap {
+        Gap {
+            offset,
+            height,
+            motion,
+        }
+    }
+
+    #[test]
+    fn parses_settings_and_pipe_groups() {
+        let content = "\
+# A short one
+name = \"First steps\"
+stars = 3 5
+finish = 150
+
+250 0
+180 -40.5 110   # narrower
+200 20 120 10 2 15 1.5
+";
+        let level = Level::parse("01".to_string(), "01.level", content).unwrap();
+
+        assert_eq!(
+            level,
+            Level {
+                id: "01".to_string(),
+                name: "First steps".to_string(),
+                pipes: vec![
+                    LevelPipe {
+                        distance: 250.0,
+                        gap: gap(0.0, None, None),
+                    },
+                    LevelPipe {
+                        distance: 180.0,
+                        gap: gap(-40.5, Some(110.0), None),
+                    },
+                    LevelPipe {
+                        distance: 200.0,
+                        gap: gap(
+                            20.0,
+                            Some(120.0),
+                            PipeMotion::new(10.0, 2.0, 15.0, 1.5).ok()
+                        ),
+                    },
+                ],
+                finish: 150.0,
+                star_flaps: Some((3, 5)),
+            }
+        );
+    }
+
+    #[test]
+    fn saved_levels_parse_back_the_same() {
+        let mut level = Level::empty("custom".to_string());
+        level.name = "Round \"trip\" #2 \\ back".to_string();
+        level.finish = 0.0;
+        level.pipes = vec![
+            LevelPipe {
+                distance: 300.0,
+                gap: gap(-12.25, None, None),
+            },
+            LevelPipe {
+                distance: 160.5,
+                gap: gap(80.0, Some(95.0), None),
+            },
+            LevelPipe {
+                distance: 200.0,
+                gap: gap(0.0, Some(130.0), PipeMotion::new(25.0, 1.5, 10.0, 0.8).ok()),
+            },
+        ];
+
+        for star_flaps in [None, Some((4, 9))] {
+            level.star_flaps = star_flaps;
+            let parsed = Level::parse(level.id.clone(), "custom.level", &level.contents());
+
+            assert_eq!(parsed, Ok(level.clone()));
+        }
+    }
+
+    #[test]
+    fn rejects_broken_levels() {
+        for content in [
+            "",
+            "name = Empty",
+            "0 10",
+            "-100 10",
+            "200",
+            "200 up",
+            "200 10\nstars = 5 3",
+            "200 10\nspeed = 2",
+            "200 10\nname = \"Unclosed",
+            "200 10\nname = \"Closed\" early\"",
+            "200 10\nname = \"Bad \\n escape\"",
+        ] {
+            assert!(
+                Level::parse("bad".to_string(), "bad.level", content).is_err(),
+                "`{}`",
+                content
+            );
+        }
+    }
+
+    #[test]
+    fn empty_levels_go_straight_to_the_finish() {
+        let mut course = Course::new(Level::empty("empty".to_string()), 0.0);
+
+        assert_eq!(course.due_pipe(), None);
+        course.advance(150.0);
+        assert_eq!(course.due_pipe(), None);
+        assert_eq!(course.finish_x(288.0, 1.0), 288.0 + DEFAULT_FINISH - 150.0);
+    }
+
+    #[test]
+    fn awards_stars_by_flaps() {
+        let mut level = Level::empty("stars".to_string());
+
+        assert_eq!(level.stars(100), 3);
+
+        level.star_flaps = Some((5, 8));
+        assert_eq!(level.stars(5), 3);
+        assert_eq!(level.stars(6), 2);
+        assert_eq!(level.stars(8), 2);
+        assert_eq!(level.stars(9), 1);
+    }
+}
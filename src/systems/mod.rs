@@ -1,3 +1,20 @@
+pub mod assets;
+pub mod autopilot;
+pub mod config;
+pub mod controls;
+pub mod difficulty;
+pub mod environment;
+pub mod gamemode;
+pub mod hotreload;
+pub mod level;
 pub mod physics;
+pub mod pipegenerators;
+pub mod reachability;
+pub mod replay;
 pub mod scenemanagement;
+pub mod simulation;
+pub mod sprites;
 pub mod storage;
+pub mod timestep;
+pub mod transitioneffects;
+pub mod tuning;
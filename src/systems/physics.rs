@@ -1,13 +1,843 @@
-use tetra::graphics::Rectangle;
+use std::sync::Arc;
+
+use tetra::graphics::{ImageData, Rectangle};
+use tetra::math::Vec2;
+
+/// Pixels at or below this alpha are see-through and never collide.
+const ALPHA_THRESHOLD: f32 = 0.5;
+/// How many segments approximate a full circle in shape outlines.
+const OUTLINE_SEGMENTS: u32 = 24;
+/// How far shapes may move between two exact checks while sweeping, in pixels.
+const SWEEP_STEP: f32 = 1.0;
 
 pub trait PhysicsBody {
+    /// An axis-aligned box around everything the body can collide with.
     fn get_collision_rect(&mut self) -> Rectangle;
-    fn collides_with(&mut self, obj: &Rectangle) -> bool;
+
+    fn get_shapes(&mut self) -> Vec<Shape> {
+        vec![Shape::Aabb(self.get_collision_rect())]
+    }
+}
+
+/// Which pixels of a sprite are solid, built from its alpha channel.
+pub struct CollisionMask {
+    width: usize,
+    height: usize,
+    solid: Vec<bool>,
+}
+
+impl CollisionMask {
+    pub fn from_image(image: &ImageData, region: Rectangle) -> CollisionMask {
+        let width = region.width as usize;
+        let height = region.height as usize;
+        let mut solid = Vec::with_capacity(width * height);
+
+        for y in 0..height {
+            for x in 0..width {
+                let position = Vec2::new(region.x as i32 + x as i32, region.y as i32 + y as i32);
+                solid.push(image.get_pixel_color(position).a > ALPHA_THRESHOLD);
+            }
+        }
+
+        CollisionMask {
+            width,
+            height,
+            solid,
+        }
+    }
+
+    pub fn is_solid(&self, x: i32, y: i32) -> bool {
+        x >= 0
+            && y >= 0
+            && (x as usize) < self.width
+            && (y as usize) < self.height
+            && self.solid[y as usize * self.width + x as usize]
+    }
+}
+
+/// A collision mask placed in the world the same way its sprite is drawn.
+#[derive(Clone)]
+pub struct PlacedMask {
+    pub mask: Arc<CollisionMask>,
+    pub position: Vec2<f32>,
+    pub origin: Vec2<f32>,
+    pub rotation: f32,
+    pub scale: Vec2<f32>,
+}
+
+impl PlacedMask {
+    fn to_world(&self, local: Vec2<f32>) -> Vec2<f32> {
+        let (sin, cos) = self.rotation.sin_cos();
+        let offset = (local - self.origin) * self.scale;
+        self.position
+            + Vec2::new(
+                offset.x * cos - offset.y * sin,
+                offset.x * sin + offset.y * cos,
+            )
+    }
+
+    fn to_local(&self, world: Vec2<f32>) -> Vec2<f32> {
+        let (sin, cos) = self.rotation.sin_cos();
+        let offset = world - self.position;
+        self.origin
+            + Vec2::new(
+                offset.x * cos + offset.y * sin,
+                offset.y * cos - offset.x * sin,
+            ) / self.scale
+    }
+
+    /// Centers of the solid pixels that may lie within `area`, in world coordinates.
+    fn solid_points_within(&self, area: Rectangle) -> impl Iterator<Item = Vec2<f32>> + '_ {
+        let corners = [
+            self.to_local(Vec2::new(area.x, area.y)),
+            self.to_local(Vec2::new(area.x + area.width, area.y)),
+            self.to_local(Vec2::new(area.x, area.y + area.height)),
+            self.to_local(Vec2::new(area.x + area.width, area.y + area.height)),
+        ];
+        let local = bounds_of(&corners);
+        let columns = local.x.floor().max(0.0) as usize
+            ..((local.x + local.width).ceil().max(0.0) as usize).min(self.mask.width);
+        let rows = local.y.floor().max(0.0) as usize
+            ..((local.y + local.height).ceil().max(0.0) as usize).min(self.mask.height);
+
+        rows.flat_map(move |y| columns.clone().map(move |x| (x, y)))
+            .filter(move |&(x, y)| self.mask.solid[y * self.mask.width + x])
+            .map(move |(x, y)| self.to_world(Vec2::new(x as f32 + 0.5, y as f32 + 0.5)))
+    }
+
+    fn contains(&self, point: Vec2<f32>) -> bool {
+        let local = self.to_local(point);
+        self.mask
+            .is_solid(local.x.floor() as i32, local.y.floor() as i32)
+    }
+
+    pub fn bounds(&self) -> Rectangle {
+        let width = self.mask.width as f32;
+        let height = self.mask.height as f32;
+        let corners = [
+            self.to_world(Vec2::new(0.0, 0.0)),
+            self.to_world(Vec2::new(width, 0.0)),
+            self.to_world(Vec2::new(0.0, height)),
+            self.to_world(Vec2::new(width, height)),
+        ];
+
+        bounds_of(&corners)
+    }
+}
+
+#[derive(Clone)]
+pub enum Shape {
+    Aabb(Rectangle),
+    Circle {
+        center: Vec2<f32>,
+        radius: f32,
+    },
+    /// A rectangle of `half_extents` rotated by `rotation` radians around its center.
+    Obb {
+        center: Vec2<f32>,
+        half_extents: Vec2<f32>,
+        rotation: f32,
+    },
+    /// Every point within `radius` of the segment from `start` to `end`.
+    Capsule {
+        start: Vec2<f32>,
+        end: Vec2<f32>,
+        radius: f32,
+    },
+    Mask(PlacedMask),
+}
+
+impl Shape {
+    pub fn bounds(&self) -> Rectangle {
+        match self {
+            Shape::Aabb(rect) => *rect,
+            Shape::Circle { center, radius } => Rectangle::new(
+                center.x - radius,
+                center.y - radius,
+                radius * 2.0,
+                radius * 2.0,
+            ),
+            Shape::Obb {
+                center,
+                half_extents,
+                rotation,
+            } => bounds_of(
+                &Obb {
+                    center: *center,
+                    half_extents: *half_extents,
+                    rotation: *rotation,
+                }
+                .corners(),
+            ),
+            Shape::Capsule { start, end, radius } => {
+                let bounds = bounds_of(&[*start, *end]);
+                Rectangle::new(
+                    bounds.x - radius,
+                    bounds.y - radius,
+                    bounds.width + radius * 2.0,
+                    bounds.height + radius * 2.0,
+                )
+            }
+            Shape::Mask(mask) => mask.bounds(),
+        }
+    }
+
+    /// Points around the edge of the shape, for drawing it. Masks are outlined by their frame.
+    pub fn outline(&self) -> Vec<Vec2<f32>> {
+        match self {
+            Shape::Mask(mask) => {
+                let width = mask.mask.width as f32;
+                let height = mask.mask.height as f32;
+                vec![
+                    mask.to_world(Vec2::new(0.0, 0.0)),
+                    mask.to_world(Vec2::new(width, 0.0)),
+                    mask.to_world(Vec2::new(width, height)),
+                    mask.to_world(Vec2::new(0.0, height)),
+                ]
+            }
+            _ => match self.to_primitive() {
+                Primitive::Box(obb) => obb.corners().to_vec(),
+                Primitive::Round { start, end, radius } => {
+                    let axis = end - start;
+                    let angle = axis.y.atan2(axis.x);
+                    let half_turn = (0..=OUTLINE_SEGMENTS / 2)
+                        .map(|i| i as f32 / OUTLINE_SEGMENTS as f32 * std::f32::consts::TAU);
+                    let cap = |center: Vec2<f32>, from: f32| {
+                        half_turn.clone().map(move |step| {
+                            let (sin, cos) = (from + step).sin_cos();
+                            center + Vec2::new(cos, sin) * radius
+                        })
+                    };
+                    cap(end, angle - std::f32::consts::FRAC_PI_2)
+                        .chain(cap(start, angle + std::f32::consts::FRAC_PI_2))
+                        .collect()
+                }
+            },
+        }
+    }
+
+    pub fn contains(&self, point: Vec2<f32>) -> bool {
+        match self {
+            Shape::Mask(mask) => mask.contains(point),
+            _ => match self.to_primitive() {
+                Primitive::Box(obb) => obb.distance_to_point(point) <= 0.0,
+                Primitive::Round { start, end, radius } => {
+                    point_segment_distance(point, start, end) <= radius
+                }
+            },
+        }
+    }
+
+    fn to_primitive(&self) -> Primitive {
+        match *self {
+            Shape::Aabb(rect) => Primitive::Box(Obb {
+                center: Vec2::new(rect.x + rect.width / 2.0, rect.y + rect.height / 2.0),
+                half_extents: Vec2::new(rect.width / 2.0, rect.height / 2.0),
+                rotation: 0.0,
+            }),
+            Shape::Circle { center, radius } => Primitive::Round {
+                start: center,
+                end: center,
+                radius,
+            },
+            Shape::Obb {
+                center,
+                half_extents,
+                rotation,
+            } => Primitive::Box(Obb {
+                center,
+                half_extents,
+                rotation,
+            }),
+            Shape::Capsule { start, end, radius } => Primitive::Round { start, end, radius },
+            Shape::Mask(_) => unreachable!("masks have no primitive form"),
+        }
+    }
+}
+
+/// Every shape reduces to either a (possibly rotated) box or a swept circle.
+enum Primitive {
+    Box(Obb),
+    Round {
+        start: Vec2<f32>,
+        end: Vec2<f32>,
+        radius: f32,
+    },
+}
+
+struct Obb {
+    center: Vec2<f32>,
+    half_extents: Vec2<f32>,
+    rotation: f32,
+}
+
+impl Obb {
+    fn axes(&self) -> [Vec2<f32>; 2] {
+        let (sin, cos) = self.rotation.sin_cos();
+        [Vec2::new(cos, sin), Vec2::new(-sin, cos)]
+    }
+
+    fn corners(&self) -> [Vec2<f32>; 4] {
+        let [x_axis, y_axis] = self.axes();
+        let x = x_axis * self.half_extents.x;
+        let y = y_axis * self.half_extents.y;
+        [
+            self.center - x - y,
+            self.center + x - y,
+            self.center + x + y,
+            self.center - x + y,
+        ]
+    }
+
+    fn to_local(&self, point: Vec2<f32>) -> Vec2<f32> {
+        let [x_axis, y_axis] = self.axes();
+        let offset = point - self.center;
+        Vec2::new(offset.dot(x_axis), offset.dot(y_axis))
+    }
+
+    /// Distance from the point to the box, or zero if it is inside.
+    fn distance_to_point(&self, point: Vec2<f32>) -> f32 {
+        let local = self.to_local(point);
+        let outside = Vec2::new(
+            (local.x.abs() - self.half_extents.x).max(0.0),
+            (local.y.abs() - self.half_extents.y).max(0.0),
+        );
+        outside.magnitude()
+    }
+
+    /// Half the length of the box projected onto `axis`.
+    fn projected_radius(&self, axis: Vec2<f32>) -> f32 {
+        let [x_axis, y_axis] = self.axes();
+        self.half_extents.x * x_axis.dot(axis).abs() + self.half_extents.y * y_axis.dot(axis).abs()
+    }
+
+    fn intersects_segment(&self, start: Vec2<f32>, end: Vec2<f32>) -> bool {
+        // Clip the segment against both slabs of the box in its local space.
+        let start = self.to_local(start);
+        let direction = self.to_local(end) - start;
+        let mut t_min = 0.0f32;
+        let mut t_max = 1.0f32;
+
+        for (origin, delta, extent) in [
+            (start.x, direction.x, self.half_extents.x),
+            (start.y, direction.y, self.half_extents.y),
+        ] {
+            if delta.abs() < f32::EPSILON {
+                if origin.abs() > extent {
+                    return false;
+                }
+            } else {
+                let t1 = (-extent - origin) / delta;
+                let t2 = (extent - origin) / delta;
+                t_min = t_min.max(t1.min(t2));
+                t_max = t_max.min(t1.max(t2));
+                if t_min > t_max {
+                    return false;
+                }
+            }
+        }
+
+        true
+    }
+
+    fn distance_to_segment(&self, start: Vec2<f32>, end: Vec2<f32>) -> f32 {
+        if self.intersects_segment(start, end) {
+            return 0.0;
+        }
+
+        // Two disjoint convex shapes are closest at a vertex of one of them.
+        let from_corners = self
+            .corners()
+            .iter()
+            .map(|corner| point_segment_distance(*corner, start, end))
+            .fold(f32::INFINITY, f32::min);
+        let from_ends = self
+            .distance_to_point(start)
+            .min(self.distance_to_point(end));
+        from_corners.min(from_ends)
+    }
+
+    fn intersects(&self, other: &Obb) -> bool {
+        let offset = other.center - self.center;
+        self.axes().iter().chain(other.axes().iter()).all(|axis| {
+            offset.dot(*axis).abs() <= self.projected_radius(*axis) + other.projected_radius(*axis)
+        })
+    }
+}
+
+fn point_segment_distance(point: Vec2<f32>, start: Vec2<f32>, end: Vec2<f32>) -> f32 {
+    let segment = end - start;
+    let length_squared = segment.magnitude_squared();
+    if length_squared <= f32::EPSILON {
+        return point.distance(start);
+    }
+
+    let t = ((point - start).dot(segment) / length_squared).clamp(0.0, 1.0);
+    point.distance(start + segment * t)
+}
+
+fn segments_intersect(a1: Vec2<f32>, a2: Vec2<f32>, b1: Vec2<f32>, b2: Vec2<f32>) -> bool {
+    let cross = |o: Vec2<f32>, a: Vec2<f32>, b: Vec2<f32>| {
+        (a.x - o.x) * (b.y - o.y) - (a.y - o.y) * (b.x - o.x)
+    };
+    let d1 = cross(b1, b2, a1);
+    let d2 = cross(b1, b2, a2);
+    let d3 = cross(a1, a2, b1);
+    let d4 = cross(a1, a2, b2);
+    (d1 > 0.0) != (d2 > 0.0) && (d3 > 0.0) != (d4 > 0.0)
+}
+
+fn segment_segment_distance(a1: Vec2<f32>, a2: Vec2<f32>, b1: Vec2<f32>, b2: Vec2<f32>) -> f32 {
+    if segments_intersect(a1, a2, b1, b2) {
+        return 0.0;
+    }
+
+    [
+        point_segment_distance(a1, b1, b2),
+        point_segment_distance(a2, b1, b2),
+        point_segment_distance(b1, a1, a2),
+        point_segment_distance(b2, a1, a2),
+    ]
+    .iter()
+    .fold(f32::INFINITY, |min, distance| min.min(*distance))
+}
+
+fn bounds_of(points: &[Vec2<f32>]) -> Rectangle {
+    let min = points
+        .iter()
+        .fold(points[0], |min, p| Vec2::partial_min(min, *p));
+    let max = points
+        .iter()
+        .fold(points[0], |max, p| Vec2::partial_max(max, *p));
+    Rectangle::new(min.x, min.y, max.x - min.x, max.y - min.y)
+}
+
+/// Whether two shapes overlap, for every pairing of shape kinds.
+pub fn intersects(shape1: &Shape, shape2: &Shape) -> bool {
+    if !check_rect_collision(&shape1.bounds(), &shape2.bounds()) {
+        return false;
+    }
+
+    match (shape1, shape2) {
+        (Shape::Mask(mask1), Shape::Mask(mask2)) => {
+            // Walk the smaller mask where it overlaps the larger one, and sample that.
+            let (small, large) = if mask1.mask.solid.len() <= mask2.mask.solid.len() {
+                (mask1, mask2)
+            } else {
+                (mask2, mask1)
+            };
+            small
+                .solid_points_within(large.bounds())
+                .any(|point| large.contains(point))
+        }
+        (Shape::Mask(mask), shape) | (shape, Shape::Mask(mask)) => mask
+            .solid_points_within(shape.bounds())
+            .any(|point| shape.contains(point)),
+        _ => match (shape1.to_primitive(), shape2.to_primitive()) {
+            (Primitive::Box(obb1), Primitive::Box(obb2)) => obb1.intersects(&obb2),
+            (Primitive::Box(obb), Primitive::Round { start, end, radius })
+            | (Primitive::Round { start, end, radius }, Primitive::Box(obb)) => {
+                obb.distance_to_segment(start, end) <= radius
+            }
+            (
+                Primitive::Round {
+                    start: start1,
+                    end: end1,
+                    radius: radius1,
+                },
+                Primitive::Round {
+                    start: start2,
+                    end: end2,
+                    radius: radius2,
+                },
+            ) => segment_segment_distance(start1, end1, start2, end2) <= radius1 + radius2,
+        },
+    }
 }
 
-pub fn check_collision(rect1: &Rectangle, rect2: &Rectangle) -> bool {
+pub fn check_collision(shapes1: &[Shape], shapes2: &[Shape]) -> bool {
+    shapes1
+        .iter()
+        .any(|shape1| shapes2.iter().any(|shape2| intersects(shape1, shape2)))
+}
+
+pub fn check_rect_collision(rect1: &Rectangle, rect2: &Rectangle) -> bool {
     rect1.x < rect2.x + rect2.width
         && rect1.x + rect1.width > rect2.x
         && rect1.y < rect2.y + rect2.height
         && rect1.y + rect1.height > rect2.y
 }
+
+/// When, as a fraction of `motion`, a rectangle moving along `motion` first touches `target`.
+pub fn sweep_aabb(moving: &Rectangle, motion: Vec2<f32>, target: &Rectangle) -> Option<f32> {
+    if check_rect_collision(moving, target) {
+        return Some(0.0);
+    }
+
+    let mut entry = f32::NEG_INFINITY;
+    let mut exit = f32::INFINITY;
+
+    for (position, size, delta, target_position, target_size) in [
+        (moving.x, moving.width, motion.x, target.x, target.width),
+        (moving.y, moving.height, motion.y, target.y, target.height),
+    ] {
+        let near = target_position - (position + size);
+        let far = target_position + target_size - position;
+
+        if delta.abs() < f32::EPSILON {
+            if near >= 0.0 || far <= 0.0 {
+                return None;
+            }
+        } else {
+            let (t1, t2) = (near / delta, far / delta);
+            entry = entry.max(t1.min(t2));
+            exit = exit.min(t1.max(t2));
+        }
+    }
+
+    if entry <= exit && (0.0..=1.0).contains(&entry) {
+        Some(entry)
+    } else {
+        None
+    }
+}
+
+/// Finds the earliest time in `[0, 1]` at which shapes travelling along `motion` touch
+/// `targets`, which all lie within `target_bounds`. `shapes_at` gives the moving shapes
+/// at each point in time.
+///
+/// Swept boxes rule out misses cheaply, then the exact shapes are checked in small
+/// increments from the first possible contact, so nothing is skipped between steps.
+pub fn time_of_impact<F>(
+    motion: Vec2<f32>,
+    shapes_at: F,
+    target_bounds: &Rectangle,
+    targets: &[Shape],
+) -> Option<f32>
+where
+    F: Fn(f32) -> Vec<Shape>,
+{
+    // Cover whatever rotation or animation happens during the motion.
+    let start = bounds_of_shapes(&shapes_at(0.0));
+    let end = bounds_of_shapes(&shapes_at(1.0));
+    let moving = bounds_of(&[
+        Vec2::new(start.x, start.y),
+        Vec2::new(start.x + start.width, start.y + start.height),
+        Vec2::new(end.x, end.y) - motion,
+        Vec2::new(end.x + end.width, end.y + end.height) - motion,
+    ]);
+    sweep_aabb(&moving, motion, target_bounds)?;
+
+    let first_contact = targets
+        .iter()
+        .filter_map(|target| sweep_aabb(&moving, motion, &target.bounds()))
+        .fold(None, |first: Option<f32>, t| {
+            Some(first.map_or(t, |first| first.min(t)))
+        })?;
+
+    let remaining = 1.0 - first_contact;
+    let samples = ((motion.magnitude() * remaining) / SWEEP_STEP)
+        .ceil()
+        .max(1.0) as u32;
+    (0..=samples)
+        .map(|i| first_contact + remaining * i as f32 / samples as f32)
+        .find(|t| check_collision(&shapes_at(*t), targets))
+}
+
+fn bounds_of_shapes(shapes: &[Shape]) -> Rectangle {
+    let corners: Vec<Vec2<f32>> = shapes
+        .iter()
+        .map(Shape::bounds)
+        .flat_map(|bounds| {
+            vec![
+                Vec2::new(bounds.x, bounds.y),
+                Vec2::new(bounds.x + bounds.width, bounds.y + bounds.height),
+            ]
+        })
+        .collect();
+    bounds_of(&corners)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::f32::consts::FRAC_PI_4;
+
+    /// A 20x20 mask, solid wherever `solid` says so.
+    fn mask(solid: impl Fn(usize, usize) -> bool) -> Arc<CollisionMask> {
+        Arc::new(CollisionMask {
+            width: 20,
+            height: 20,
+            solid: (0..400).map(|i| solid(i % 20, i / 20)).collect(),
+        })
+    }
+
+    fn placed(mask: Arc<CollisionMask>, center: Vec2<f32>) -> Shape {
+        Shape::Mask(PlacedMask {
+            mask,
+            position: center,
+            origin: Vec2::new(10.0, 10.0),
+            rotation: 0.0,
+            scale: Vec2::one(),
+        })
+    }
+
+    /// One shape of every kind, about 20 pixels across and centered on `center`.
+    fn shapes_at(center: Vec2<f32>) -> Vec<(&'static str, Shape)> {
+        vec![
+            (
+                "aabb",
+                Shape::Aabb(Rectangle::new(center.x - 10.0, center.y - 10.0, 20.0, 20.0)),
+            ),
+            (
+                "circle",
+                Shape::Circle {
+                    center,
+                    radius: 10.0,
+                },
+            ),
+            (
+                "obb",
+                Shape::Obb {
+                    center,
+                    half_extents: Vec2::new(10.0, 5.0),
+                    rotation: FRAC_PI_4,
+                },
+            ),
+            (
+                "capsule",
+                Shape::Capsule {
+                    start: center - Vec2::new(5.0, 0.0),
+                    end: center + Vec2::new(5.0, 0.0),
+                    radius: 5.0,
+                },
+            ),
+            ("mask", placed(mask(|_, _| true), center)),
+        ]
+    }
+
+    #[test]
+    fn every_pair_of_shapes_overlaps_on_the_same_spot() {
+        for (name1, shape1) in shapes_at(Vec2::new(50.0, 50.0)) {
+            for (name2, shape2) in shapes_at(Vec2::new(53.0, 48.0)) {
+                assert!(intersects(&shape1, &shape2), "{} and {}", name1, name2);
+            }
+        }
+    }
+
+    #[test]
+    fn every_pair_of_shapes_misses_far_apart() {
+        for (name1, shape1) in shapes_at(Vec2::new(50.0, 50.0)) {
+            for (name2, shape2) in shapes_at(Vec2::new(90.0, 50.0)) {
+                assert!(!intersects(&shape1, &shape2), "{} and {}", name1, name2);
+                assert!(!intersects(&shape2, &shape1), "{} and {}", name2, name1);
+            }
+        }
+    }
+
+    #[test]
+    fn aabb_and_circle_miss_at_the_corner() {
+        let aabb = Shape::Aabb(Rectangle::new(0.0, 0.0, 20.0, 20.0));
+        let circle = Shape::Circle {
+            center: Vec2::new(28.0, 28.0),
+            radius: 10.0,
+        };
+
+        // Their bounds overlap, but the circle curves away from the corner.
+        assert!(check_rect_collision(&aabb.bounds(), &circle.bounds()));
+        assert!(!intersects(&aabb, &circle));
+        assert!(!intersects(&circle, &aabb));
+    }
+
+    #[test]
+    fn obb_and_aabb_miss_beside_the_rotated_corner() {
+        let aabb = Shape::Aabb(Rectangle::new(0.0, 0.0, 20.0, 20.0));
+        let diamond = Shape::Obb {
+            center: Vec2::new(33.0, 33.0),
+            half_extents: Vec2::new(10.0, 10.0),
+            rotation: FRAC_PI_4,
+        };
+        let touching = Shape::Obb {
+            center: Vec2::new(27.0, 27.0),
+            half_extents: Vec2::new(10.0, 10.0),
+            rotation: FRAC_PI_4,
+        };
+
+        assert!(check_rect_collision(&aabb.bounds(), &diamond.bounds()));
+        assert!(!intersects(&aabb, &diamond));
+        assert!(intersects(&aabb, &touching));
+    }
+
+    #[test]
+    fn capsules_collide_along_their_whole_length() {
+        let capsule = Shape::Capsule {
+            start: Vec2::new(0.0, 0.0),
+            end: Vec2::new(100.0, 0.0),
+            radius: 5.0,
+        };
+        let circle = |x: f32, y: f32| Shape::Circle {
+            center: Vec2::new(x, y),
+            radius: 5.0,
+        };
+
+        assert!(intersects(&capsule, &circle(50.0, 9.0)));
+        assert!(!intersects(&capsule, &circle(50.0, 11.0)));
+        assert!(intersects(&capsule, &circle(107.0, 0.0)));
+        assert!(!intersects(&capsule, &circle(108.0, 8.0)));
+
+        let crossing = Shape::Capsule {
+            start: Vec2::new(50.0, -50.0),
+            end: Vec2::new(50.0, 50.0),
+            radius: 1.0,
+        };
+        assert!(intersects(&capsule, &crossing));
+    }
+
+    #[test]
+    fn masks_only_collide_with_their_solid_pixels() {
+        // A ring with a 10x10 hole in the middle.
+        let ring = mask(|x, y| !(5..15).contains(&x) || !(5..15).contains(&y));
+        let center = Vec2::new(50.0, 50.0);
+        let inside = Shape::Circle {
+            center,
+            radius: 4.0,
+        };
+        let across = Shape::Circle {
+            center,
+            radius: 7.0,
+        };
+
+        assert!(!intersects(&placed(ring.clone(), center), &inside));
+        assert!(!intersects(&inside, &placed(ring.clone(), center)));
+        assert!(intersects(&placed(ring.clone(), center), &across));
+
+        let dot = placed(mask(|x, y| x == 10 && y == 10), center);
+        assert!(!intersects(&placed(ring.clone(), center), &dot));
+        assert!(!intersects(&dot, &placed(ring.clone(), center)));
+        assert!(intersects(
+            &dot,
+            &placed(ring, center + Vec2::new(7.0, 0.0))
+        ));
+    }
+
+    #[test]
+    fn masks_follow_their_scale_and_rotation() {
+        let bar = mask(|_, y| y < 2);
+        let stretched = Shape::Mask(PlacedMask {
+            mask: bar.clone(),
+            position: Vec2::zero(),
+            origin: Vec2::zero(),
+            rotation: 0.0,
+            scale: Vec2::new(3.0, 1.0),
+        });
+        // Bigger than a stretched pixel, so it always covers the middle of one.
+        let point = |x: f32, y: f32| Shape::Circle {
+            center: Vec2::new(x, y),
+            radius: 2.0,
+        };
+
+        assert!(intersects(&stretched, &point(55.0, 1.0)));
+        assert!(!intersects(&stretched, &point(63.0, 1.0)));
+        assert!(!intersects(&stretched, &point(30.0, 5.0)));
+
+        let upright = Shape::Mask(PlacedMask {
+            mask: bar,
+            position: Vec2::zero(),
+            origin: Vec2::zero(),
+            rotation: std::f32::consts::FRAC_PI_2,
+            scale: Vec2::one(),
+        });
+        assert!(intersects(&upright, &point(-1.0, 15.0)));
+        assert!(!intersects(&upright, &point(15.0, 3.0)));
+    }
+
+    /// A bird-sized circle at `x` flying along `motion`, against a 4 pixel wide wall at 150.
+    fn fly_at_wall(x: f32, motion: Vec2<f32>) -> Option<f32> {
+        let wall = Rectangle::new(150.0, 0.0, 4.0, 100.0);
+        time_of_impact(
+            motion,
+            |t| {
+                vec![Shape::Circle {
+                    center: Vec2::new(x, 50.0) + motion * t,
+                    radius: 10.0,
+                }]
+            },
+            &wall,
+            &[Shape::Aabb(wall)],
+        )
+    }
+
+    #[test]
+    fn sweep_aabb_finds_the_first_touch() {
+        let moving = Rectangle::new(0.0, 0.0, 10.0, 10.0);
+        let thin = Rectangle::new(100.0, 0.0, 2.0, 10.0);
+
+        assert_eq!(sweep_aabb(&moving, Vec2::new(180.0, 0.0), &thin), Some(0.5));
+        assert_eq!(sweep_aabb(&moving, Vec2::new(-180.0, 0.0), &thin), None);
+        assert_eq!(sweep_aabb(&moving, Vec2::new(180.0, 40.0), &thin), None);
+    }
+
+    #[test]
+    fn sweep_aabb_includes_both_ends_of_the_motion() {
+        let moving = Rectangle::new(0.0, 0.0, 10.0, 10.0);
+        let target = Rectangle::new(100.0, 0.0, 10.0, 10.0);
+
+        assert_eq!(
+            sweep_aabb(&moving, Vec2::new(90.0, 0.0), &target),
+            Some(1.0)
+        );
+        assert_eq!(sweep_aabb(&moving, Vec2::new(89.0, 0.0), &target), None);
+
+        let flush = Rectangle::new(90.0, 0.0, 10.0, 10.0);
+        assert_eq!(sweep_aabb(&flush, Vec2::new(5.0, 0.0), &target), Some(0.0));
+        assert_eq!(sweep_aabb(&flush, Vec2::new(-5.0, 0.0), &target), None);
+        assert_eq!(sweep_aabb(&flush, Vec2::zero(), &target), None);
+    }
+
+    #[test]
+    fn sweep_aabb_reports_overlaps_at_the_start() {
+        let moving = Rectangle::new(95.0, 0.0, 10.0, 10.0);
+        let target = Rectangle::new(100.0, 0.0, 10.0, 10.0);
+
+        assert_eq!(
+            sweep_aabb(&moving, Vec2::new(-50.0, 0.0), &target),
+            Some(0.0)
+        );
+        assert_eq!(sweep_aabb(&moving, Vec2::zero(), &target), Some(0.0));
+    }
+
+    #[test]
+    fn fast_shapes_dont_tunnel_through_thin_walls() {
+        // Both ends of the motion are clear of the wall, but the middle isn't.
+        let t = fly_at_wall(0.0, Vec2::new(300.0, 0.0)).unwrap();
+
+        let first_touch = 140.0 / 300.0;
+        assert!(
+            t >= first_touch && t <= first_touch + SWEEP_STEP / 300.0,
+            "{}",
+            t
+        );
+    }
+
+    #[test]
+    fn time_of_impact_includes_the_end_of_the_motion() {
+        assert_eq!(fly_at_wall(0.0, Vec2::new(141.0, 0.0)), Some(1.0));
+        // Touching without overlapping isn't an impact.
+        assert_eq!(fly_at_wall(0.0, Vec2::new(140.0, 0.0)), None);
+        assert_eq!(fly_at_wall(0.0, Vec2::new(139.0, 0.0)), None);
+    }
+
+    #[test]
+    fn time_of_impact_is_zero_when_already_overlapping() {
+        assert_eq!(fly_at_wall(145.0, Vec2::new(50.0, 0.0)), Some(0.0));
+        assert_eq!(fly_at_wall(145.0, Vec2::new(-50.0, 0.0)), Some(0.0));
+        assert_eq!(fly_at_wall(145.0, Vec2::zero()), Some(0.0));
+    }
+
+    #[test]
+    fn time_of_impact_misses_when_moving_away() {
+        assert_eq!(fly_at_wall(0.0, Vec2::new(-300.0, 0.0)), None);
+        assert_eq!(fly_at_wall(0.0, Vec2::new(300.0, -200.0)), None);
+    }
+}
@@ -0,0 +1,485 @@
+use std::f32::consts::TAU;
+use std::fmt;
+use std::fs;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::prefabs::pipes::PipeMotion;
+use crate::systems::difficulty::Difficulty;
+use crate::systems::reachability::Envelope;
+
+/// Pipe groups per full wave of `SineTunnel`.
+const TUNNEL_PERIOD: f32 = 8.0;
+/// Height of one step of `Staircase`, in pixels.
+const STAIR_HEIGHT: f32 = 40.0;
+/// How the gap of a `Squeeze` section compares to the usual one.
+const SQUEEZE_FACTOR: f32 = 0.85;
+/// Pipe groups between two `Squeeze` sections, and in each of them.
+const SQUEEZE_EVERY: u32 = 8;
+const SQUEEZE_LENGTH: u32 = 4;
+
+/// Where a pipe group's gap goes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Gap {
+    /// Pixels above (negative) or below (positive) the usual height.
+    pub offset: f32,
+    /// Vertical space between the pipes, or `None` for the current difficulty's.
+    pub height: Option<f32>,
+    /// How the group moves, or `None` for the tuning's `pipe_motion`.
+    pub motion: Option<PipeMotion>,
+}
+
+impl Gap {
+    /// Parses `offset`, `offset height` or `offset height amplitude period gap_swing speed`.
+    pub fn parse(line: &str) -> Result<Gap, String> {
+        let numbers = line
+            .split_whitespace()
+            .map(|number| number.parse::<f32>().ok().filter(|n| n.is_finite()))
+            .collect::<Option<Vec<f32>>>();
+        let (offset, height, motion) = match numbers.as_deref() {
+            Some(&[offset]) => (offset, None, None),
+            Some(&[offset, height]) => (offset, Some(height), None),
+            Some(&[offset, height, amplitude, period, gap_swing, speed]) => (
+                offset,
+                Some(height),
+                Some(PipeMotion::new(amplitude, period, gap_swing, speed)?),
+            ),
+            _ => {
+                return Err(
+                    "expected `offset`, `offset height` or `offset height amplitude period gap_swing speed`"
+                        .to_string(),
+                )
+            }
+        };
+        if height.is_some_and(|height| height <= 0.0) {
+            return Err("the gap height must be positive".to_string());
+        }
+
+        Ok(Gap {
+            offset,
+            height,
+            motion,
+        })
+    }
+}
+
+/// Writes the gap the way `Gap::parse` reads it. The motion needs a height before it,
+/// so it is left out of gaps without one.
+impl fmt::Display for Gap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.offset)?;
+        if let Some(height) = self.height {
+            write!(f, " {}", height)?;
+            if let Some(motion) = self.motion {
+                write!(
+                    f,
+                    " {} {} {} {}",
+                    motion.amplitude, motion.period, motion.gap_swing, motion.speed
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Lays out the gaps of a run, one pipe group at a time.
+pub trait PipeGenerator {
+    /// Starts over from the first gap, with any randomness seeded from `seed`.
+    fn reset(&mut self, seed: u64);
+
+    /// Places the next gap. `envelope` tells how far the bird can get from the previous
+    /// one, which generators stay within unless the layout is hand-authored.
+    fn next_gap(&mut self, difficulty: &Difficulty, envelope: &Envelope) -> Gap;
+}
+
+/// The original game: every gap at a random height.
+pub struct Uniform {
+    rng: StdRng,
+    previous_offset: Option<f32>,
+}
+
+impl Uniform {
+    pub fn new(seed: u64) -> Uniform {
+        Uniform {
+            rng: StdRng::seed_from_u64(seed),
+            previous_offset: None,
+        }
+    }
+}
+
+impl PipeGenerator for Uniform {
+    fn reset(&mut self, seed: u64) {
+        *self = Uniform::new(seed);
+    }
+
+    fn next_gap(&mut self, difficulty: &Difficulty, envelope: &Envelope) -> Gap {
+        let (low, high) = envelope.bounds(self.previous_offset, difficulty.gap_offset_range);
+        let offset = if low < high {
+            self.rng.gen_range(low..high)
+        } else {
+            low
+        };
+        self.previous_offset = Some(offset);
+
+        Gap {
+            offset,
+            height: None,
+            motion: None,
+        }
+    }
+}
+
+/// Gaps following a sine wave across the whole offset range, starting at a random
+/// point of the wave.
+pub struct SineTunnel {
+    index: u32,
+    previous_offset: Option<f32>,
+}
+
+impl SineTunnel {
+    pub fn new(seed: u64) -> SineTunnel {
+        SineTunnel {
+            index: StdRng::seed_from_u64(seed).gen_range(0..TUNNEL_PERIOD as u32),
+            previous_offset: None,
+        }
+    }
+}
+
+impl PipeGenerator for SineTunnel {
+    fn reset(&mut self, seed: u64) {
+        *self = SineTunnel::new(seed);
+    }
+
+    fn next_gap(&mut self, difficulty: &Difficulty, envelope: &Envelope) -> Gap {
+        let range = difficulty.gap_offset_range;
+        let wave = (self.index as f32 * TAU / TUNNEL_PERIOD).sin() * range;
+        let (low, high) = envelope.bounds(self.previous_offset, range);
+        let offset = wave.clamp(low, high);
+
+        self.index += 1;
+        self.previous_offset = Some(offset);
+
+        Gap {
+            offset,
+            height: None,
+            motion: None,
+        }
+    }
+}
+
+/// Gaps climbing or descending by `STAIR_HEIGHT` at a time, turning around at the
+/// ends of the offset range.
+pub struct Staircase {
+    rng: StdRng,
+    direction: f32,
+    previous_offset: Option<f32>,
+}
+
+impl Staircase {
+    pub fn new(seed: u64) -> Staircase {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let direction = if rng.gen() { 1.0 } else { -1.0 };
+
+        Staircase {
+            rng,
+            direction,
+            previous_offset: None,
+        }
+    }
+}
+
+impl PipeGenerator for Staircase {
+    fn reset(&mut self, seed: u64) {
+        *self = Staircase::new(seed);
+    }
+
+    fn next_gap(&mut self, difficulty: &Difficulty, envelope: &Envelope) -> Gap {
+        let range = difficulty.gap_offset_range;
+        let target = match self.previous_offset {
+            Some(previous) => {
+                if (previous + self.direction * STAIR_HEIGHT).abs() > range {
+                    self.direction = -self.direction;
+                }
+                previous + self.direction * STAIR_HEIGHT
+            }
+            None => self.rng.gen_range(-range..=range),
+        };
+        let (low, high) = envelope.bounds(self.previous_offset, range);
+        let offset = target.clamp(low, high);
+        self.previous_offset = Some(offset);
+
+        Gap {
+            offset,
+            height: None,
+            motion: None,
+        }
+    }
+}
+
+/// Random gaps like `Uniform`, broken up by sections of narrower gaps at one height.
+pub struct Squeeze {
+    uniform: Uniform,
+    count: u32,
+}
+
+impl Squeeze {
+    pub fn new(seed: u64) -> Squeeze {
+        Squeeze {
+            uniform: Uniform::new(seed),
+            count: 0,
+        }
+    }
+}
+
+impl PipeGenerator for Squeeze {
+    fn reset(&mut self, seed: u64) {
+        *self = Squeeze::new(seed);
+    }
+
+    fn next_gap(&mut self, difficulty: &Difficulty, envelope: &Envelope) -> Gap {
+        let squeezing = self.count % (SQUEEZE_EVERY + SQUEEZE_LENGTH) >= SQUEEZE_EVERY;
+        self.count += 1;
+
+        let previous = self.uniform.previous_offset;
+        if !squeezing || previous.is_none() {
+            return self.uniform.next_gap(difficulty, envelope);
+        }
+
+        // Stay at the previous height, so the narrower gap is the only new challenge.
+        let (low, high) = envelope.bounds(previous, difficulty.gap_offset_range);
+        let offset = previous.unwrap_or(0.0).clamp(low, high);
+        self.uniform.previous_offset = Some(offset);
+
+        Gap {
+            offset,
+            height: Some(difficulty.pipe_gap * SQUEEZE_FACTOR),
+            motion: None,
+        }
+    }
+}
+
+/// Hand-authored gaps, repeated from the start once they run out. They are used as
+/// written, so it's up to the author to keep them within reach.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sequence {
+    gaps: Vec<Gap>,
+    index: usize,
+}
+
+impl Sequence {
+    /// Plays `gaps` from the first, which there has to be.
+    pub fn new(gaps: Vec<Gap>) -> Result<Sequence, String> {
+        if gaps.is_empty() {
+            return Err("no gaps in the sequence".to_string());
+        }
+
+        Ok(Sequence { gaps, index: 0 })
+    }
+
+    pub fn gaps(&self) -> &[Gap] {
+        &self.gaps
+    }
+
+    /// Reads gaps from a file with one `offset`, `offset height` or
+    /// `offset height amplitude period gap_swing speed` per line, where `#` starts a
+    /// comment.
+    pub fn load(path: &str) -> Result<Sequence, String> {
+        let content =
+            fs::read_to_string(path).map_err(|err| format!("can't read `{}`: {}", path, err))?;
+
+        let mut gaps = Vec::new();
+        for (index, line) in content.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let gap = Gap::parse(line).map_err(|err| format!("{}:{}: {}", path, index + 1, err))?;
+            gaps.push(gap);
+        }
+
+        Sequence::new(gaps).map_err(|err| format!("{}: {}", path, err))
+    }
+}
+
+impl PipeGenerator for Sequence {
+    fn reset(&mut self, _seed: u64) {
+        self.index = 0;
+    }
+
+    fn next_gap(&mut self, _difficulty: &Difficulty, _envelope: &Envelope) -> Gap {
+        let gap = self.gaps[self.index];
+        self.index = (self.index + 1) % self.gaps.len();
+        gap
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ENVELOPE: Envelope = Envelope {
+        max_climb: 50.0,
+        max_drop: 70.0,
+    };
+
+    fn difficulty(gap_offset_range: f32) -> Difficulty {
+        Difficulty {
+            spawn_interval: 1.3,
+            pipe_gap: 120.0,
+            gap_offset_range,
+            scroll_speed: 180.0,
+        }
+    }
+
+    /// Runs `generator` over several seeds, with the offset range shrinking halfway
+    /// through, and checks every gap against the range and the envelope.
+    fn assert_stays_in_bounds(mut generator: impl PipeGenerator) {
+        for seed in 0..20 {
+            generator.reset(seed);
+            let mut previous = None;
+
+            for index in 0..200 {
+                let difficulty = difficulty(if index < 100 { 100.0 } else { 40.0 });
+                let range = difficulty.gap_offset_range;
+                let (low, high) = ENVELOPE.bounds(previous, range);
+                let gap = generator.next_gap(&difficulty, &ENVELOPE);
+
+                assert!(
+                    gap.offset >= low && gap.offset <= high && gap.offset.abs() <= range,
+                    "seed {}, gap {}: {} is outside {}..={}",
+                    seed,
+                    index,
+                    gap.offset,
+                    low,
+                    high
+                );
+                if let Some(height) = gap.height {
+                    assert!(height > 0.0 && height <= difficulty.pipe_gap);
+                }
+                previous = Some(gap.offset);
+            }
+        }
+    }
+
+    #[test]
+    fn uniform_stays_in_bounds() {
+        assert_stays_in_bounds(Uniform::new(0));
+    }
+
+    #[test]
+    fn sine_tunnel_stays_in_bounds() {
+        assert_stays_in_bounds(SineTunnel::new(0));
+    }
+
+    #[test]
+    fn staircase_stays_in_bounds() {
+        assert_stays_in_bounds(Staircase::new(0));
+    }
+
+    #[test]
+    fn squeeze_stays_in_bounds() {
+        assert_stays_in_bounds(Squeeze::new(0));
+    }
+
+    #[test]
+    fn squeeze_narrows_gaps_in_sections() {
+        let mut squeeze = Squeeze::new(7);
+        let heights: Vec<Option<f32>> = (0..SQUEEZE_EVERY + SQUEEZE_LENGTH + 1)
+            .map(|_| squeeze.next_gap(&difficulty(100.0), &ENVELOPE).height)
+            .collect();
+
+        let squeezed = Some(120.0 * SQUEEZE_FACTOR);
+        let section = SQUEEZE_EVERY as usize..(SQUEEZE_EVERY + SQUEEZE_LENGTH) as usize;
+        for (index, height) in heights.iter().enumerate() {
+            let expected = if section.contains(&index) {
+                squeezed
+            } else {
+                None
+            };
+            assert_eq!(*height, expected, "gap {}", index);
+        }
+    }
+
+    #[test]
+    fn generators_repeat_their_layout_for_a_seed() {
+        let layout = |generator: &mut dyn PipeGenerator, seed: u64| -> Vec<Gap> {
+            generator.reset(seed);
+            (0..50)
+                .map(|_| generator.next_gap(&difficulty(100.0), &ENVELOPE))
+                .collect()
+        };
+        let generators: [&mut dyn PipeGenerator; 4] = [
+            &mut Uniform::new(0),
+            &mut SineTunnel::new(0),
+            &mut Staircase::new(0),
+            &mut Squeeze::new(0),
+        ];
+
+        for generator in generators {
+            let first = layout(generator, 42);
+            assert_eq!(layout(generator, 42), first);
+        }
+    }
+
+    #[test]
+    fn rejects_empty_sequences() {
+        assert!(Sequence::new(Vec::new()).is_err());
+    }
+
+    #[test]
+    fn sequence_plays_its_gaps_as_written_and_repeats() {
+        // Hand-authored gaps aren't held to the envelope or the offset range.
+        let gaps = vec![
+            Gap::parse("0").unwrap(),
+            Gap::parse("-150 90").unwrap(),
+            Gap::parse("150 130 20 2 10 1.2").unwrap(),
+        ];
+        let mut sequence = Sequence::new(gaps.clone()).unwrap();
+        let mut play = |count: usize| -> Vec<Gap> {
+            (0..count)
+                .map(|_| sequence.next_gap(&difficulty(40.0), &ENVELOPE))
+                .collect()
+        };
+
+        let played = play(7);
+        assert_eq!(played[..3], gaps[..]);
+        assert_eq!(played[3..6], gaps[..]);
+        assert_eq!(played[6], gaps[0]);
+
+        sequence.reset(5);
+        assert_eq!(sequence.next_gap(&difficulty(40.0), &ENVELOPE), gaps[0]);
+    }
+
+    #[test]
+    fn gaps_parse_back_what_they_write() {
+        for line in [
+            "0",
+            "-60",
+            "-120 100",
+            "-60 120 30 2 0 1",
+            "12.5 99.5 1 0.5 3 1.25",
+        ] {
+            let gap = Gap::parse(line).unwrap();
+            assert_eq!(gap.to_string(), line);
+            assert_eq!(Gap::parse(&gap.to_string()), Ok(gap));
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_gaps() {
+        for line in [
+            "",
+            "up",
+            "0 -10",
+            "0 0",
+            "0 100 1 2 3",
+            "0 100 1 0 3 1",
+            "0 inf",
+        ] {
+            assert!(Gap::parse(line).is_err(), "`{}`", line);
+        }
+    }
+}
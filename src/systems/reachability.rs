@@ -0,0 +1,340 @@
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::sync::Arc;
+
+use tetra::math::Vec2;
+
+use crate::prefabs::bird::BIRD_X;
+use crate::prefabs::pipes::{PipeGeometry, PipeGroup, PipeSpawner};
+use crate::systems::difficulty::{Difficulty, Progress};
+use crate::systems::physics::{self, CollisionMask, PhysicsBody, Shape};
+use crate::systems::sprites::Sprites;
+use crate::systems::tuning::Tuning;
+
+/// Distance from the bird's origin to the corners of its 34x24 frame, so a circle with
+/// this radius covers the bird at any rotation.
+const BIRD_RADIUS: f32 = 20.9;
+/// How many pipes have to be passed for a seed to count as survivable.
+pub const PIPES_PER_SEED: i32 = 30;
+/// Flights closer in height than this are merged while searching, in pixels. A coarser
+/// step is faster but misses more ways through.
+const HEIGHT_STEP: f32 = 1.0;
+
+/// How far the middle of the next gap may be above or below the previous one for the
+/// bird to get from one to the other.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Envelope {
+    pub max_climb: f32,
+    pub max_drop: f32,
+}
+
+impl Envelope {
+    /// Works out the envelope from the bird's physics. The bird is assumed to leave a gap
+    /// at the top of a flap, and to climb by flapping every step. It may leave from one
+    /// end of the gap and arrive at the other, but must have room for a flap once there.
+    pub fn new(tuning: &Tuning, difficulty: &Difficulty, pipe_width: f32) -> Envelope {
+        let dt = tuning.tick_length();
+        // While the bird is between the pipes of a group it has to stay in the gap.
+        let traversal = (pipe_width + BIRD_RADIUS * 2.0) / difficulty.scroll_speed;
+        let ticks = ((difficulty.spawn_interval - traversal) / dt).max(0.0) as u32;
+
+        let mut velocity = 0.0;
+        let mut max_drop = 0.0;
+        for _ in 0..ticks {
+            velocity += tuning.gravity * dt;
+            max_drop += velocity * dt;
+        }
+        // The first step is spent reacting.
+        let max_climb =
+            ticks.saturating_sub(1) as f32 * (tuning.flap_speed - tuning.gravity * dt) * dt;
+
+        let slack = difficulty.pipe_gap - BIRD_RADIUS * 2.0;
+        let flap_height = tuning.flap_speed * tuning.flap_speed / (2.0 * tuning.gravity);
+        let margin = slack - flap_height;
+
+        Envelope {
+            max_climb: (max_climb + margin).max(0.0),
+            max_drop: (max_drop + margin).max(0.0),
+        }
+    }
+
+    /// Shrinks the envelope by `by` pixels each way, e.g. to leave room for moving pipes.
+    pub fn narrowed(self, by: f32) -> Envelope {
+        Envelope {
+            max_climb: (self.max_climb - by).max(0.0),
+            max_drop: (self.max_drop - by).max(0.0),
+        }
+    }
+
+    /// The lowest and highest offset the gap after one at `previous` may have, within
+    /// `offset_range` of the usual height. If the range shrank away from the previous
+    /// gap, both are the nearest offset left.
+    pub fn bounds(&self, previous: Option<f32>, offset_range: f32) -> (f32, f32) {
+        let (mut low, mut high) = (-offset_range, offset_range);
+        if let Some(previous) = previous {
+            low = low.max(previous - self.max_climb);
+            high = high.min(previous + self.max_drop);
+        }
+
+        if low < high {
+            (low, high)
+        } else {
+            let nearest = previous.unwrap_or(0.0).clamp(-offset_range, offset_range);
+            (nearest, nearest)
+        }
+    }
+}
+
+/// Searches every seed in `seeds` for a way past `PIPES_PER_SEED` pipes, and returns the
+/// seeds where none was found. The search only follows flights the bird could really
+/// fly, so a seed left out certainly has a way through. A returned seed most likely has
+/// none, but may still have one that `thin_out` dropped.
+pub fn check_seeds(tuning: &Tuning, sprites: &Sprites, seeds: Range<u64>) -> Vec<u64> {
+    let geometry = PipeGeometry::new(sprites.pipes_size, tuning.pipe_width, tuning.pipe_gap);
+
+    seeds
+        .filter(|seed| !is_survivable(tuning, geometry, &sprites.pipe_masks, *seed))
+        .collect()
+}
+
+/// A possible height and vertical speed of the bird.
+#[derive(Clone, Copy)]
+struct Flight {
+    y: f32,
+    velocity: f32,
+}
+
+impl Flight {
+    /// Mirrors `Bird::update` followed by an optional flap.
+    fn step(self, tuning: &Tuning, dt: f32, flap: bool) -> Flight {
+        let mut velocity = self.velocity + tuning.gravity * dt;
+        let mut y = self.y + velocity * dt;
+        if y <= 12.0 {
+            y = 12.0;
+            velocity = 0.0;
+        }
+        if flap {
+            velocity = -tuning.flap_speed;
+        }
+
+        Flight { y, velocity }
+    }
+}
+
+/// Runs the pipes for `seed` the way `Simulation::step` does, while tracking every
+/// height the bird could be at.
+fn is_survivable(
+    tuning: &Tuning,
+    geometry: PipeGeometry,
+    masks: &[Arc<CollisionMask>; 2],
+    seed: u64,
+) -> bool {
+    let dt = tuning.tick_length();
+    let mut spawner = PipeSpawner::new(tuning.spawn_interval);
+    spawner.start();
+    let mut generator = tuning.mode.pipe_generator(seed);
+    let mut pipes: Vec<PipeGroup> = Vec::new();
+    let mut score = 0;
+    let mut run_time = 0.0;
+
+    // The first flap starts the run.
+    let mut flights = vec![Flight {
+        y: tuning.screen_height as f32 / 2.0,
+        velocity: -tuning.flap_speed,
+    }];
+
+    loop {
+        run_time += dt;
+        let progress = match tuning.difficulty.progress {
+            Progress::Score => score as f32,
+            Progress::Time => run_time,
+        };
+        let difficulty = tuning.difficulty.at(progress, tuning.base_difficulty());
+        spawner.set_spawn_interval(difficulty.spawn_interval);
+
+        for pipe_group in &mut pipes {
+            if !pipe_group.has_scored && pipe_group.gap_center().x <= BIRD_X {
+                pipe_group.has_scored = true;
+                score += 1;
+            }
+            pipe_group.update(difficulty.scroll_speed, dt);
+        }
+        if score >= PIPES_PER_SEED {
+            return true;
+        }
+
+        // Like the bird's circle, whole pipe sprites only err towards calling a seed
+        // impossible, and they are much quicker to check than the masks.
+        let nearby: Vec<Shape> = pipes
+            .iter_mut()
+            .filter(|pipe_group| pipe_group.alive)
+            .filter_map(|pipe_group| {
+                let bounds = pipe_group.get_collision_rect();
+                let overlaps = bounds.x < BIRD_X + BIRD_RADIUS
+                    && bounds.x + bounds.width > BIRD_X - BIRD_RADIUS;
+                overlaps.then(|| {
+                    pipe_group
+                        .pipes_mut()
+                        .map(|pipe| Shape::Aabb(pipe.get_collision_rect()))
+                })
+            })
+            .flatten()
+            .collect();
+        flights.retain(|flight| {
+            let bird = Shape::Circle {
+                center: Vec2::new(BIRD_X, flight.y),
+                radius: BIRD_RADIUS,
+            };
+            flight.y + BIRD_RADIUS < tuning.ground_y && !physics::check_collision(&[bird], &nearby)
+        });
+        if flights.is_empty() {
+            return false;
+        }
+
+        if spawner.should_spawn_pipe(dt) {
+            let envelope = Envelope::new(tuning, &difficulty, geometry.pipe_width)
+                .narrowed(tuning.pipe_motion.reach());
+            let gap = generator.next_gap(&difficulty, &envelope);
+            let geometry = PipeGeometry {
+                gap_height: gap.height.unwrap_or(difficulty.pipe_gap),
+                ..geometry
+            };
+
+            let mut pipe_group = PipeGroup::new(geometry, masks.clone());
+            pipe_group.reset(
+                tuning.screen_width as f32,
+                gap.offset,
+                gap.motion.unwrap_or(tuning.pipe_motion),
+            );
+            pipes.retain(|pipe_group| pipe_group.alive);
+            pipes.push(pipe_group);
+        }
+
+        flights = thin_out(
+            flights
+                .iter()
+                .flat_map(|flight| {
+                    [false, true]
+                        .iter()
+                        .map(move |flap| flight.step(tuning, dt, *flap))
+                })
+                .collect(),
+        );
+    }
+}
+
+/// Keeps only the fastest rising and the fastest falling flight among those within
+/// `HEIGHT_STEP` of each other, so the search doesn't grow with every step. The flights
+/// in between usually get through the same gaps as the two kept, but nothing guarantees
+/// it, which is why the search can miss a way through yet never invents one. Ties keep
+/// the first flight, so the result only depends on the order of `flights`.
+fn thin_out(flights: Vec<Flight>) -> Vec<Flight> {
+    let mut extremes: BTreeMap<i32, (Flight, Flight)> = BTreeMap::new();
+    for flight in flights {
+        let (rising, falling) = extremes
+            .entry((flight.y / HEIGHT_STEP).round() as i32)
+            .or_insert((flight, flight));
+        if flight.velocity < rising.velocity {
+            *rising = flight;
+        }
+        if flight.velocity > falling.velocity {
+            *falling = flight;
+        }
+    }
+
+    extremes
+        .into_values()
+        .flat_map(|(rising, falling)| [rising, falling])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ENVELOPE: Envelope = Envelope {
+        max_climb: 40.0,
+        max_drop: 60.0,
+    };
+
+    /// Slow without optimizations, so CI runs it with `cargo test --release -- --ignored`.
+    #[test]
+    #[ignore]
+    fn default_tuning_has_no_impossible_seeds() {
+        let failures = check_seeds(&Tuning::default(), &Sprites::solid(), 0..1000);
+
+        assert_eq!(failures, Vec::<u64>::new());
+    }
+
+    #[test]
+    fn envelope_grows_with_the_gap() {
+        let tuning = Tuning::default();
+        let mut difficulty = tuning.base_difficulty();
+        let envelope = Envelope::new(&tuning, &difficulty, tuning.pipe_width);
+        difficulty.pipe_gap += 10.0;
+        let wider = Envelope::new(&tuning, &difficulty, tuning.pipe_width);
+
+        assert!(envelope.max_climb > 0.0 && envelope.max_drop > 0.0);
+        assert_eq!(wider.max_climb, envelope.max_climb + 10.0);
+        assert_eq!(wider.max_drop, envelope.max_drop + 10.0);
+    }
+
+    #[test]
+    fn envelope_is_empty_without_time_between_pipes() {
+        let tuning = Tuning::default();
+        let difficulty = Difficulty {
+            spawn_interval: 0.1,
+            pipe_gap: BIRD_RADIUS * 2.0,
+            ..tuning.base_difficulty()
+        };
+
+        assert_eq!(
+            Envelope::new(&tuning, &difficulty, tuning.pipe_width),
+            Envelope {
+                max_climb: 0.0,
+                max_drop: 0.0,
+            }
+        );
+    }
+
+    #[test]
+    fn narrowed_shrinks_both_ways_down_to_zero() {
+        assert_eq!(
+            ENVELOPE.narrowed(10.0),
+            Envelope {
+                max_climb: 30.0,
+                max_drop: 50.0,
+            }
+        );
+        assert_eq!(
+            ENVELOPE.narrowed(50.0),
+            Envelope {
+                max_climb: 0.0,
+                max_drop: 10.0,
+            }
+        );
+        assert_eq!(ENVELOPE.narrowed(0.0), ENVELOPE);
+    }
+
+    #[test]
+    fn bounds_use_the_whole_range_for_the_first_gap() {
+        assert_eq!(ENVELOPE.bounds(None, 100.0), (-100.0, 100.0));
+    }
+
+    #[test]
+    fn bounds_stay_within_reach_of_the_previous_gap() {
+        assert_eq!(ENVELOPE.bounds(Some(0.0), 100.0), (-40.0, 60.0));
+        assert_eq!(ENVELOPE.bounds(Some(80.0), 100.0), (40.0, 100.0));
+        assert_eq!(ENVELOPE.bounds(Some(-80.0), 100.0), (-100.0, -20.0));
+    }
+
+    #[test]
+    fn bounds_fall_back_to_the_nearest_offset_when_out_of_reach() {
+        // The range shrank, e.g. with difficulty, leaving the previous gap out of reach.
+        assert_eq!(ENVELOPE.bounds(Some(-150.0), 50.0), (-50.0, -50.0));
+        assert_eq!(ENVELOPE.bounds(Some(150.0), 50.0), (50.0, 50.0));
+
+        let closed = ENVELOPE.narrowed(100.0);
+        assert_eq!(closed.bounds(Some(20.0), 100.0), (20.0, 20.0));
+    }
+}
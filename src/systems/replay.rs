@@ -0,0 +1,478 @@
+use crate::prefabs::bird::Hitbox;
+use crate::prefabs::pipes::PipeMotion;
+use crate::systems::difficulty::{Difficulty, DifficultyCurve, Keyframe, Progress};
+use crate::systems::gamemode::GameMode;
+use crate::systems::pipegenerators::{Gap, Sequence};
+use crate::systems::simulation::Input;
+use crate::systems::tuning::Tuning;
+
+const MAGIC: &[u8; 4] = b"FLPR";
+pub const REPLAY_VERSION: u16 = 1;
+
+const ACTION_FLAP: u8 = 1;
+
+/// A recorded run: everything needed to play it back through a `Simulation`.
+#[derive(Clone, Debug)]
+pub struct Replay {
+    pub seed: u64,
+    pub tuning: Tuning,
+    pub inputs: Vec<(u32, Input)>,
+}
+
+impl Replay {
+    pub fn new(seed: u64, tuning: Tuning) -> Replay {
+        Replay {
+            seed,
+            tuning,
+            inputs: Vec::new(),
+        }
+    }
+
+    pub fn record(&mut self, frame: u32, input: Input) {
+        if input != Input::None {
+            self.inputs.push((frame, input));
+        }
+    }
+
+    /// Encodes the replay as a header followed by delta-encoded frame indices.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(64 + self.inputs.len() * 2);
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&REPLAY_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&self.seed.to_le_bytes());
+        bytes.extend_from_slice(&self.tuning.gravity.to_le_bytes());
+        bytes.extend_from_slice(&self.tuning.scroll_speed.to_le_bytes());
+        bytes.extend_from_slice(&self.tuning.tick_rate.to_le_bytes());
+        bytes.push(match self.tuning.bird_hitbox {
+            Hitbox::Mask => 0,
+            Hitbox::Circle => 1,
+            Hitbox::Capsule => 2,
+            Hitbox::Box => 3,
+        });
+        bytes.extend_from_slice(&self.tuning.pipe_gap.to_le_bytes());
+        bytes.extend_from_slice(&self.tuning.pipe_width.to_le_bytes());
+        bytes.extend_from_slice(&self.tuning.screen_width.to_le_bytes());
+        bytes.extend_from_slice(&self.tuning.screen_height.to_le_bytes());
+        bytes.extend_from_slice(&self.tuning.flap_speed.to_le_bytes());
+        bytes.extend_from_slice(&self.tuning.spawn_interval.to_le_bytes());
+        bytes.extend_from_slice(&self.tuning.gap_offset_range.to_le_bytes());
+        bytes.extend_from_slice(&self.tuning.ground_y.to_le_bytes());
+        bytes.push(match self.tuning.difficulty.progress {
+            Progress::Score => 0,
+            Progress::Time => 1,
+        });
+        write_varint(&mut bytes, self.tuning.difficulty.keyframes.len() as u32);
+        for keyframe in &self.tuning.difficulty.keyframes {
+            let difficulty = keyframe.difficulty;
+            for value in [
+                keyframe.at,
+                difficulty.spawn_interval,
+                difficulty.pipe_gap,
+                difficulty.gap_offset_range,
+                difficulty.scroll_speed,
+            ] {
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+        bytes.push(match self.tuning.mode {
+            GameMode::Classic => 0,
+            GameMode::Tunnel => 1,
+            GameMode::Stairs => 2,
+            GameMode::Squeeze => 3,
+            GameMode::Sequence(_) => 4,
+        });
+        if let GameMode::Sequence(sequence) = &self.tuning.mode {
+            let gaps = sequence.gaps();
+            write_varint(&mut bytes, gaps.len() as u32);
+            for gap in gaps {
+                bytes.extend_from_slice(&gap.offset.to_le_bytes());
+                // A height of zero stands for the difficulty's pipe gap.
+                bytes.extend_from_slice(&gap.height.unwrap_or(0.0).to_le_bytes());
+                match gap.motion {
+                    Some(motion) => {
+                        bytes.push(1);
+                        write_motion(&mut bytes, &motion);
+                    }
+                    None => bytes.push(0),
+                }
+            }
+        }
+        write_motion(&mut bytes, &self.tuning.pipe_motion);
+        write_varint(&mut bytes, self.inputs.len() as u32);
+
+        let mut last_frame = 0;
+        for &(frame, input) in &self.inputs {
+            write_varint(&mut bytes, frame - last_frame);
+            bytes.push(match input {
+                Input::Flap => ACTION_FLAP,
+                Input::None => unreachable!("record never keeps frames without input"),
+            });
+            last_frame = frame;
+        }
+
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Replay, String> {
+        let mut reader = Reader { bytes, offset: 0 };
+
+        if reader.take(4)? != MAGIC {
+            return Err("not a replay file".to_string());
+        }
+        let version = u16::from_le_bytes(reader.array()?);
+        if version != REPLAY_VERSION {
+            return Err(format!("unsupported replay version {}", version));
+        }
+
+        let seed = u64::from_le_bytes(reader.array()?);
+        let tuning = Tuning {
+            gravity: f32::from_le_bytes(reader.array()?),
+            scroll_speed: f32::from_le_bytes(reader.array()?),
+            tick_rate: f32::from_le_bytes(reader.array()?),
+            bird_hitbox: match reader.take(1)?[0] {
+                0 => Hitbox::Mask,
+                1 => Hitbox::Circle,
+                2 => Hitbox::Capsule,
+                3 => Hitbox::Box,
+                hitbox => return Err(format!("unknown hitbox {}", hitbox)),
+            },
+            pipe_gap: f32::from_le_bytes(reader.array()?),
+            pipe_width: f32::from_le_bytes(reader.array()?),
+            screen_width: i32::from_le_bytes(reader.array()?),
+            screen_height: i32::from_le_bytes(reader.array()?),
+            flap_speed: f32::from_le_bytes(reader.array()?),
+            spawn_interval: f32::from_le_bytes(reader.array()?),
+            gap_offset_range: f32::from_le_bytes(reader.array()?),
+            ground_y: f32::from_le_bytes(reader.array()?),
+            difficulty: reader.difficulty()?,
+            mode: reader.mode()?,
+            pipe_motion: reader.motion()?,
+        };
+
+        let count = reader.varint()?;
+        let mut inputs = Vec::with_capacity(count as usize);
+        let mut frame = 0u32;
+        for _ in 0..count {
+            frame = frame
+                .checked_add(reader.varint()?)
+                .ok_or_else(|| "frame index overflow".to_string())?;
+            let input = match reader.take(1)?[0] {
+                ACTION_FLAP => Input::Flap,
+                action => return Err(format!("unknown action {}", action)),
+            };
+            inputs.push((frame, input));
+        }
+
+        Ok(Replay {
+            seed,
+            tuning,
+            inputs,
+        })
+    }
+}
+
+fn write_varint(bytes: &mut Vec<u8>, mut value: u32) {
+    while value >= 0x80 {
+        bytes.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+fn write_motion(bytes: &mut Vec<u8>, motion: &PipeMotion) {
+    for value in [
+        motion.amplitude,
+        motion.period,
+        motion.gap_swing,
+        motion.speed,
+    ] {
+        bytes.extend_from_slice(&value.to_le_bytes());
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, count: usize) -> Result<&'a [u8], String> {
+        let end = self.offset + count;
+        if end > self.bytes.len() {
+            return Err("unexpected end of replay".to_string());
+        }
+        let slice = &self.bytes[self.offset..end];
+        self.offset = end;
+        Ok(slice)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], String> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.take(N)?);
+        Ok(array)
+    }
+
+    fn f32(&mut self) -> Result<f32, String> {
+        Ok(f32::from_le_bytes(self.array()?))
+    }
+
+    fn difficulty(&mut self) -> Result<DifficultyCurve, String> {
+        let progress = match self.take(1)?[0] {
+            0 => Progress::Score,
+            1 => Progress::Time,
+            progress => return Err(format!("unknown difficulty progress {}", progress)),
+        };
+
+        let count = self.varint()?;
+        let mut keyframes = Vec::new();
+        for _ in 0..count {
+            keyframes.push(Keyframe {
+                at: self.f32()?,
+                difficulty: Difficulty {
+                    spawn_interval: self.f32()?,
+                    pipe_gap: self.f32()?,
+                    gap_offset_range: self.f32()?,
+                    scroll_speed: self.f32()?,
+                },
+            });
+        }
+
+        Ok(DifficultyCurve {
+            progress,
+            keyframes,
+        })
+    }
+
+    fn mode(&mut self) -> Result<GameMode, String> {
+        Ok(match self.take(1)?[0] {
+            0 => GameMode::Classic,
+            1 => GameMode::Tunnel,
+            2 => GameMode::Stairs,
+            3 => GameMode::Squeeze,
+            4 => {
+                let count = self.varint()?;
+                if count == 0 {
+                    return Err("empty pipe sequence".to_string());
+                }
+                let mut gaps = Vec::new();
+                for _ in 0..count {
+                    let offset = self.f32()?;
+                    let height = self.f32()?;
+                    let motion = match self.take(1)?[0] {
+                        0 => None,
+                        _ => Some(self.motion()?),
+                    };
+                    gaps.push(Gap {
+                        offset,
+                        height: Some(height).filter(|height| *height > 0.0),
+                        motion,
+                    });
+                }
+                GameMode::Sequence(Sequence::new(gaps)?)
+            }
+            mode => return Err(format!("unknown game mode {}", mode)),
+        })
+    }
+
+    fn motion(&mut self) -> Result<PipeMotion, String> {
+        PipeMotion::new(self.f32()?, self.f32()?, self.f32()?, self.f32()?)
+    }
+
+    fn varint(&mut self) -> Result<u32, String> {
+        let mut value = 0u32;
+        for shift in (0..35).step_by(7) {
+            let byte = self.take(1)?[0];
+            value |= u32::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err("malformed frame index".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn varint_bytes(value: u32) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        write_varint(&mut bytes, value);
+        bytes
+    }
+
+    fn read_varint(bytes: &[u8]) -> Result<u32, String> {
+        Reader { bytes, offset: 0 }.varint()
+    }
+
+    /// A replay that uses every part of the format.
+    fn replay() -> Replay {
+        let motion = PipeMotion::new(20.0, 3.0, 10.0, 1.2).unwrap();
+        let tuning = Tuning {
+            screen_width: 320,
+            screen_height: 480,
+            gravity: 1000.0,
+            scroll_speed: 150.0,
+            tick_rate: 120.0,
+            flap_speed: 400.0,
+            spawn_interval: 1.5,
+            gap_offset_range: 80.0,
+            ground_y: 380.0,
+            bird_hitbox: Hitbox::Capsule,
+            pipe_gap: 110.0,
+            pipe_width: 60.0,
+            difficulty: DifficultyCurve {
+                progress: Progress::Time,
+                keyframes: vec![Keyframe {
+                    at: 30.0,
+                    difficulty: Difficulty {
+                        spawn_interval: 1.2,
+                        pipe_gap: 100.0,
+                        gap_offset_range: 90.0,
+                        scroll_speed: 180.0,
+                    },
+                }],
+            },
+            mode: GameMode::Sequence(
+                Sequence::new(vec![
+                    Gap {
+                        offset: -40.0,
+                        height: None,
+                        motion: None,
+                    },
+                    Gap {
+                        offset: 25.0,
+                        height: Some(130.0),
+                        motion: Some(motion),
+                    },
+                ])
+                .unwrap(),
+            ),
+            pipe_motion: motion,
+        };
+
+        let mut replay = Replay::new(u64::MAX - 7, tuning);
+        for frame in [1, 2, 130, 131, 20_000, 3_000_000] {
+            replay.record(frame, Input::Flap);
+        }
+        replay
+    }
+
+    #[test]
+    fn round_trips_through_bytes() {
+        let replay = replay();
+        let decoded = Replay::from_bytes(&replay.to_bytes()).unwrap();
+
+        assert_eq!(decoded.seed, replay.seed);
+        assert_eq!(decoded.tuning, replay.tuning);
+        assert_eq!(decoded.inputs, replay.inputs);
+    }
+
+    #[test]
+    fn round_trips_an_empty_run_with_the_default_tuning() {
+        let replay = Replay::new(0, Tuning::default());
+        let decoded = Replay::from_bytes(&replay.to_bytes()).unwrap();
+
+        assert_eq!(decoded.seed, 0);
+        assert_eq!(decoded.tuning, Tuning::default());
+        assert!(decoded.inputs.is_empty());
+    }
+
+    #[test]
+    fn only_records_flaps() {
+        let mut replay = Replay::new(1, Tuning::default());
+        replay.record(3, Input::None);
+        replay.record(4, Input::Flap);
+
+        assert_eq!(replay.inputs, vec![(4, Input::Flap)]);
+    }
+
+    #[test]
+    fn varints_use_as_few_bytes_as_needed() {
+        assert_eq!(varint_bytes(0), vec![0x00]);
+        assert_eq!(varint_bytes(127), vec![0x7f]);
+        assert_eq!(varint_bytes(128), vec![0x80, 0x01]);
+        assert_eq!(varint_bytes(16_383), vec![0xff, 0x7f]);
+        assert_eq!(varint_bytes(16_384), vec![0x80, 0x80, 0x01]);
+        assert_eq!(varint_bytes(u32::MAX), vec![0xff, 0xff, 0xff, 0xff, 0x0f]);
+    }
+
+    #[test]
+    fn varints_round_trip() {
+        for value in [
+            0,
+            1,
+            127,
+            128,
+            255,
+            16_383,
+            16_384,
+            1 << 21,
+            1 << 28,
+            u32::MAX,
+        ] {
+            assert_eq!(read_varint(&varint_bytes(value)), Ok(value));
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_varints() {
+        // Continues past the five bytes a u32 can take.
+        assert!(read_varint(&[0xff, 0xff, 0xff, 0xff, 0xff, 0x01]).is_err());
+        // Ends while promising more bytes.
+        assert!(read_varint(&[0x80]).is_err());
+        assert!(read_varint(&[]).is_err());
+    }
+
+    #[test]
+    fn rejects_other_versions() {
+        let mut bytes = replay().to_bytes();
+        bytes[4..6].copy_from_slice(&(REPLAY_VERSION - 1).to_le_bytes());
+        assert_eq!(
+            Replay::from_bytes(&bytes).unwrap_err(),
+            format!("unsupported replay version {}", REPLAY_VERSION - 1)
+        );
+
+        bytes[4..6].copy_from_slice(&(REPLAY_VERSION + 1).to_le_bytes());
+        assert!(Replay::from_bytes(&bytes).is_err());
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        let mut bytes = replay().to_bytes();
+        bytes[0] = b'X';
+        assert_eq!(
+            Replay::from_bytes(&bytes).unwrap_err(),
+            "not a replay file".to_string()
+        );
+    }
+
+    #[test]
+    fn rejects_truncated_replays() {
+        let bytes = replay().to_bytes();
+        for length in 0..bytes.len() {
+            assert!(
+                Replay::from_bytes(&bytes[..length]).is_err(),
+                "accepted the first {} bytes",
+                length
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_frame_indices_that_overflow() {
+        let mut bytes = Replay::new(1, Tuning::default()).to_bytes();
+        // Swap the empty input list for flaps on the last frame and the one after.
+        assert_eq!(bytes.pop(), Some(0));
+        write_varint(&mut bytes, 2);
+        for delta in [u32::MAX, 1] {
+            write_varint(&mut bytes, delta);
+            bytes.push(ACTION_FLAP);
+        }
+
+        assert_eq!(
+            Replay::from_bytes(&bytes).unwrap_err(),
+            "frame index overflow".to_string()
+        );
+    }
+}
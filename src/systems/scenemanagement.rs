@@ -1,40 +1,264 @@
-use tetra::graphics::{self, Color};
+use std::ops::Range;
+
+use tetra::graphics::{self, Canvas, Color};
+use tetra::time;
 use tetra::window;
-use tetra::{Context, State};
+use tetra::{Context, Event, State};
+
+use crate::scenes::{preloader::PreloaderScene, title::TitleScene, Scene, Transition};
+use crate::systems::config::Config;
+use crate::systems::transitioneffects::ActiveEffect;
+
+/// A call `SceneStack` makes on one of its scenes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Hook {
+    Enter,
+    Exit,
+    Pause,
+    Resume,
+}
+
+impl Hook {
+    fn call(self, scene: &mut dyn Scene, ctx: &mut Context) -> tetra::Result {
+        match self {
+            Hook::Enter => scene.on_enter(ctx),
+            Hook::Exit => scene.on_exit(ctx),
+            Hook::Pause => scene.on_pause(ctx),
+            Hook::Resume => scene.on_resume(ctx),
+        }
+    }
+}
+
+/// The stack of scenes, and which of their hooks each transition calls in which order.
+/// The hooks are called through `hook`, so the stack can be followed without a window.
+struct SceneStack<S> {
+    scenes: Vec<S>,
+}
 
-use crate::scenes::{title::TitleScene, Scene, Transition};
+impl<S> SceneStack<S> {
+    fn new() -> SceneStack<S> {
+        SceneStack { scenes: Vec::new() }
+    }
+
+    /// Pauses the current scene, if any, and enters `scene` on top of it.
+    fn push(
+        &mut self,
+        scene: S,
+        mut hook: impl FnMut(&mut S, Hook) -> tetra::Result,
+    ) -> tetra::Result {
+        if let Some(current) = self.scenes.last_mut() {
+            hook(current, Hook::Pause)?;
+        }
+        self.enter(scene, hook)
+    }
+
+    /// Exits the current scene and resumes the one below, if any.
+    fn pop(&mut self, mut hook: impl FnMut(&mut S, Hook) -> tetra::Result) -> tetra::Result {
+        self.exit(&mut hook)?;
+        self.resume(hook)
+    }
+
+    /// Exits the current scene and enters `scene` in its place.
+    fn replace(
+        &mut self,
+        scene: S,
+        mut hook: impl FnMut(&mut S, Hook) -> tetra::Result,
+    ) -> tetra::Result {
+        self.exit(&mut hook)?;
+        self.enter(scene, hook)
+    }
+
+    /// Exits scenes until only `count` are left, then resumes the new top. Does nothing
+    /// if there aren't more than that.
+    fn pop_to(
+        &mut self,
+        count: usize,
+        mut hook: impl FnMut(&mut S, Hook) -> tetra::Result,
+    ) -> tetra::Result {
+        if self.scenes.len() > count {
+            while self.scenes.len() > count {
+                self.exit(&mut hook)?;
+            }
+            self.resume(hook)?;
+        }
+
+        Ok(())
+    }
+
+    /// Exits every scene, top first.
+    fn clear(&mut self, mut hook: impl FnMut(&mut S, Hook) -> tetra::Result) -> tetra::Result {
+        while !self.scenes.is_empty() {
+            self.exit(&mut hook)?;
+        }
+
+        Ok(())
+    }
+
+    /// The scenes to update, bottom first: the one on top, and those below it down to
+    /// the topmost scene that blocks updates.
+    fn updatable_range(&self, blocks_updates: impl Fn(&S) -> bool) -> Range<usize> {
+        self.topmost_up(blocks_updates)
+    }
+
+    /// The scenes to draw, bottom first: the topmost opaque scene and those above it.
+    fn drawable_range(&self, is_opaque: impl Fn(&S) -> bool) -> Range<usize> {
+        self.topmost_up(is_opaque)
+    }
+
+    /// From the topmost scene matching `matches` to the top, or every scene if none do.
+    fn topmost_up(&self, matches: impl Fn(&S) -> bool) -> Range<usize> {
+        let first = self.scenes.iter().rposition(matches).unwrap_or(0);
+        first..self.scenes.len()
+    }
+
+    fn enter(
+        &mut self,
+        mut scene: S,
+        mut hook: impl FnMut(&mut S, Hook) -> tetra::Result,
+    ) -> tetra::Result {
+        hook(&mut scene, Hook::Enter)?;
+        self.scenes.push(scene);
+        Ok(())
+    }
+
+    fn exit(&mut self, hook: &mut impl FnMut(&mut S, Hook) -> tetra::Result) -> tetra::Result {
+        if let Some(scene) = self.scenes.last_mut() {
+            hook(scene, Hook::Exit)?;
+        }
+        self.scenes.pop();
+        Ok(())
+    }
+
+    fn resume(&mut self, mut hook: impl FnMut(&mut S, Hook) -> tetra::Result) -> tetra::Result {
+        match self.scenes.last_mut() {
+            Some(scene) => hook(scene, Hook::Resume),
+            None => Ok(()),
+        }
+    }
+}
 
 pub struct SceneManager {
-    scenes: Vec<Box<dyn Scene>>,
+    stack: SceneStack<Box<dyn Scene>>,
+    /// The effect of the last transition, while it plays. No scene is updated meanwhile,
+    /// so input can't reach either side of it.
+    effect: Option<ActiveEffect>,
+    /// What the scenes draw while an effect plays.
+    incoming: Canvas,
 }
 
 impl SceneManager {
-    pub fn new(ctx: &mut Context) -> tetra::Result<SceneManager> {
+    /// Starts with the preloader, going on to the title screen once everything is loaded.
+    pub fn new(ctx: &mut Context, config: Config) -> tetra::Result<SceneManager> {
+        let preloader = PreloaderScene::new(ctx, config, TitleScene::new)?;
+        SceneManager::start(ctx, preloader)
+    }
+
+    /// Starts with the autopilot demo once everything is loaded, going back to the title
+    /// screen once it's left.
+    pub fn demo(ctx: &mut Context, config: Config) -> tetra::Result<SceneManager> {
+        let preloader = PreloaderScene::new(ctx, config, TitleScene::attract)?;
+        SceneManager::start(ctx, preloader)
+    }
+
+    fn start(ctx: &mut Context, initial_scene: PreloaderScene) -> tetra::Result<SceneManager> {
         match window::set_mouse_visible(ctx, true) {
             Ok(_) => {
-                let initial_scene = TitleScene::new(ctx)?;
-                Ok(SceneManager {
-                    scenes: vec![Box::new(initial_scene)],
-                })
+                let (width, height) = window::get_size(ctx);
+                let mut scene_manager = SceneManager {
+                    stack: SceneStack::new(),
+                    effect: None,
+                    incoming: Canvas::new(ctx, width, height)?,
+                };
+                scene_manager
+                    .stack
+                    .push(Box::new(initial_scene), SceneManager::hooks(ctx))?;
+                Ok(scene_manager)
             }
             Err(e) => panic!("Couldn't show mouse: {:?}", e),
         }
     }
+
+    /// Calls the hooks of the scenes on the stack.
+    fn hooks(ctx: &mut Context) -> impl FnMut(&mut Box<dyn Scene>, Hook) -> tetra::Result + '_ {
+        move |scene, hook| hook.call(scene.as_mut(), ctx)
+    }
+
+    fn apply(&mut self, ctx: &mut Context, transition: Transition) -> tetra::Result {
+        let stack = &mut self.stack;
+        match transition {
+            Transition::None => {}
+            Transition::Push(scene) => stack.push(scene, SceneManager::hooks(ctx))?,
+            Transition::Pop => stack.pop(SceneManager::hooks(ctx))?,
+            Transition::Replace(scene) => stack.replace(scene, SceneManager::hooks(ctx))?,
+            Transition::PopTo(count) => stack.pop_to(count, SceneManager::hooks(ctx))?,
+            Transition::Quit => stack.clear(SceneManager::hooks(ctx))?,
+            Transition::Animated {
+                transition,
+                effect,
+                duration,
+            } => {
+                let outgoing = ActiveEffect::new(ctx, effect, duration, |ctx| {
+                    SceneManager::draw_scenes(ctx, stack)
+                })?;
+                self.apply(ctx, *transition)?;
+                self.effect = outgoing;
+            }
+        }
+
+        if self.stack.scenes.is_empty() {
+            window::quit(ctx);
+        }
+
+        Ok(())
+    }
+
+    /// Draws the topmost opaque scene of `stack` and every scene above it, bottom first.
+    fn draw_scenes(ctx: &mut Context, stack: &mut SceneStack<Box<dyn Scene>>) -> tetra::Result {
+        let drawable = stack.drawable_range(|scene| scene.is_opaque());
+        for scene in &mut stack.scenes[drawable] {
+            scene.draw(ctx)?;
+        }
+
+        Ok(())
+    }
 }
 
 impl State for SceneManager {
+    /// Updates the scenes that aren't blocked, bottom first. Only the scene on top gets
+    /// to change the stack. The others are still below it whenever they update, so a
+    /// transition from one of them would act on the wrong scene, and is dropped.
     fn update(&mut self, ctx: &mut Context) -> tetra::Result {
-        match self.scenes.last_mut() {
-            Some(active_scene) => match active_scene.update(ctx)? {
-                Transition::None => {}
-                Transition::Push(s) => {
-                    self.scenes.push(s);
-                }
-                Transition::Pop => {
-                    self.scenes.pop();
-                }
-            },
-            None => window::quit(ctx),
+        if let Some(effect) = &mut self.effect {
+            if effect.update(time::get_delta_time(ctx).as_secs_f32()) {
+                self.effect = None;
+            }
+            return Ok(());
+        }
+
+        let updatable = self.stack.updatable_range(|scene| scene.blocks_updates());
+        let (active_scene, others) = match self.stack.scenes[updatable].split_last_mut() {
+            Some(scenes) => scenes,
+            None => {
+                window::quit(ctx);
+                return Ok(());
+            }
+        };
+
+        for scene in others {
+            scene.update(ctx)?;
+        }
+
+        let transition = active_scene.update(ctx)?;
+        self.apply(ctx, transition)
+    }
+
+    fn event(&mut self, ctx: &mut Context, event: Event) -> tetra::Result {
+        if self.effect.is_some() {
+            return Ok(());
+        }
+        if let Some(active_scene) = self.stack.scenes.last_mut() {
+            let transition = active_scene.event(ctx, event)?;
+            self.apply(ctx, transition)?;
         }
 
         Ok(())
@@ -42,11 +266,252 @@ impl State for SceneManager {
 
     fn draw(&mut self, ctx: &mut Context) -> tetra::Result {
         graphics::clear(ctx, Color::BLACK);
-        match self.scenes.last_mut() {
-            Some(active_scene) => active_scene.draw(ctx),
-            None => window::quit(ctx),
+        if self.stack.scenes.is_empty() {
+            window::quit(ctx);
+        }
+
+        match &self.effect {
+            Some(effect) => {
+                graphics::set_canvas(ctx, &self.incoming);
+                graphics::clear(ctx, Color::BLACK);
+                let drawn = SceneManager::draw_scenes(ctx, &mut self.stack);
+                graphics::reset_canvas(ctx);
+                drawn?;
+                effect.draw(ctx, &self.incoming)?;
+            }
+            None => SceneManager::draw_scenes(ctx, &mut self.stack)?,
         }
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    type Log = Rc<RefCell<Vec<(&'static str, Hook)>>>;
+
+    /// Stands in for a scene, writing down each of its hooks as they are called.
+    struct FakeScene {
+        name: &'static str,
+        log: Log,
+        /// A hook that fails instead of being written down.
+        fails_on: Option<Hook>,
+        opaque: bool,
+        blocks_updates: bool,
+    }
+
+    impl FakeScene {
+        fn hook(&mut self, hook: Hook) -> tetra::Result {
+            if self.fails_on == Some(hook) {
+                return Err(tetra::TetraError::PlatformError(format!(
+                    "{} failed",
+                    self.name
+                )));
+            }
+            self.log.borrow_mut().push((self.name, hook));
+            Ok(())
+        }
+    }
+
+    /// A stack holding `names`, bottom first, with the log of entering them cleared.
+    fn stack(names: &[&'static str]) -> (SceneStack<FakeScene>, Log) {
+        let log = Log::default();
+        let mut stack = SceneStack::new();
+        for name in names {
+            stack.push(scene(name, &log), FakeScene::hook).unwrap();
+        }
+        log.borrow_mut().clear();
+
+        (stack, log)
+    }
+
+    fn scene(name: &'static str, log: &Log) -> FakeScene {
+        FakeScene {
+            name,
+            log: log.clone(),
+            fails_on: None,
+            opaque: true,
+            blocks_updates: true,
+        }
+    }
+
+    fn names(stack: &SceneStack<FakeScene>) -> Vec<&'static str> {
+        stack.scenes.iter().map(|scene| scene.name).collect()
+    }
+
+    #[test]
+    fn push_pauses_the_top_then_enters() {
+        let (mut stack, log) = stack(&["title"]);
+
+        stack.push(scene("game", &log), FakeScene::hook).unwrap();
+
+        assert_eq!(names(&stack), ["title", "game"]);
+        assert_eq!(
+            *log.borrow(),
+            [("title", Hook::Pause), ("game", Hook::Enter)]
+        );
+    }
+
+    #[test]
+    fn push_onto_an_empty_stack_only_enters() {
+        let (mut stack, log) = stack(&[]);
+
+        stack
+            .push(scene("preloader", &log), FakeScene::hook)
+            .unwrap();
+
+        assert_eq!(names(&stack), ["preloader"]);
+        assert_eq!(*log.borrow(), [("preloader", Hook::Enter)]);
+    }
+
+    #[test]
+    fn pop_exits_the_top_then_resumes_the_one_below() {
+        let (mut stack, log) = stack(&["title", "game"]);
+
+        stack.pop(FakeScene::hook).unwrap();
+
+        assert_eq!(names(&stack), ["title"]);
+        assert_eq!(
+            *log.borrow(),
+            [("game", Hook::Exit), ("title", Hook::Resume)]
+        );
+    }
+
+    #[test]
+    fn popping_the_last_scene_empties_the_stack() {
+        let (mut stack, log) = stack(&["title"]);
+
+        stack.pop(FakeScene::hook).unwrap();
+
+        assert!(stack.scenes.is_empty());
+        assert_eq!(*log.borrow(), [("title", Hook::Exit)]);
+    }
+
+    #[test]
+    fn replace_exits_the_top_then_enters_without_touching_the_rest() {
+        let (mut stack, log) = stack(&["title", "game"]);
+
+        stack
+            .replace(scene("retry", &log), FakeScene::hook)
+            .unwrap();
+
+        assert_eq!(names(&stack), ["title", "retry"]);
+        assert_eq!(
+            *log.borrow(),
+            [("game", Hook::Exit), ("retry", Hook::Enter)]
+        );
+    }
+
+    #[test]
+    fn pop_to_exits_top_first_then_resumes_once() {
+        let (mut stack, log) = stack(&["title", "game", "pause", "settings"]);
+
+        stack.pop_to(1, FakeScene::hook).unwrap();
+
+        assert_eq!(names(&stack), ["title"]);
+        assert_eq!(
+            *log.borrow(),
+            [
+                ("settings", Hook::Exit),
+                ("pause", Hook::Exit),
+                ("game", Hook::Exit),
+                ("title", Hook::Resume),
+            ]
+        );
+    }
+
+    #[test]
+    fn pop_to_does_nothing_without_more_scenes_than_asked() {
+        let (mut stack, log) = stack(&["title", "game"]);
+
+        stack.pop_to(2, FakeScene::hook).unwrap();
+        stack.pop_to(3, FakeScene::hook).unwrap();
+
+        assert_eq!(names(&stack), ["title", "game"]);
+        assert!(log.borrow().is_empty());
+    }
+
+    #[test]
+    fn clear_exits_every_scene_top_first_without_resuming() {
+        let (mut stack, log) = stack(&["title", "game", "pause"]);
+
+        stack.clear(FakeScene::hook).unwrap();
+
+        assert!(stack.scenes.is_empty());
+        assert_eq!(
+            *log.borrow(),
+            [
+                ("pause", Hook::Exit),
+                ("game", Hook::Exit),
+                ("title", Hook::Exit),
+            ]
+        );
+    }
+
+    #[test]
+    fn scenes_that_fail_to_enter_stay_off_the_stack() {
+        let (mut stack, log) = stack(&["title"]);
+        let mut broken = scene("broken", &log);
+        broken.fails_on = Some(Hook::Enter);
+
+        assert!(stack.push(broken, FakeScene::hook).is_err());
+
+        assert_eq!(names(&stack), ["title"]);
+        assert_eq!(*log.borrow(), [("title", Hook::Pause)]);
+    }
+
+    #[test]
+    fn updates_only_the_top_scene_when_it_blocks() {
+        let (stack, _) = stack(&["title", "game", "pause"]);
+
+        assert_eq!(stack.updatable_range(|scene| scene.blocks_updates), 2..3);
+    }
+
+    #[test]
+    fn updates_down_to_the_topmost_blocking_scene() {
+        let (mut stack, _) = stack(&["title", "game", "countdown", "notice"]);
+        stack.scenes[2].blocks_updates = false;
+        stack.scenes[3].blocks_updates = false;
+
+        assert_eq!(stack.updatable_range(|scene| scene.blocks_updates), 1..4);
+    }
+
+    #[test]
+    fn updates_every_scene_when_none_block() {
+        let (mut stack, _) = stack(&["title", "game"]);
+        for scene in &mut stack.scenes {
+            scene.blocks_updates = false;
+        }
+
+        assert_eq!(stack.updatable_range(|scene| scene.blocks_updates), 0..2);
+        assert_eq!(
+            SceneStack::<FakeScene>::new().updatable_range(|scene| scene.blocks_updates),
+            0..0
+        );
+    }
+
+    #[test]
+    fn draws_up_from_the_topmost_opaque_scene() {
+        let (mut stack, _) = stack(&["title", "game", "pause"]);
+
+        assert_eq!(stack.drawable_range(|scene| scene.opaque), 2..3);
+
+        stack.scenes[2].opaque = false;
+        assert_eq!(stack.drawable_range(|scene| scene.opaque), 1..3);
+    }
+
+    #[test]
+    fn draws_every_scene_when_none_are_opaque() {
+        let (mut stack, _) = stack(&["game", "pause"]);
+        for scene in &mut stack.scenes {
+            scene.opaque = false;
+        }
+
+        assert_eq!(stack.drawable_range(|scene| scene.opaque), 0..2);
+    }
+}
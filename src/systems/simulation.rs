@@ -0,0 +1,498 @@
+use tetra::math::Vec2;
+
+use crate::prefabs::bird::{Bird, Pose, BIRD_HALF_WIDTH};
+use crate::prefabs::ground::Ground;
+use crate::prefabs::pipes::{PipeGeometry, PipeGroup, PipeSpawner};
+use crate::systems::difficulty::{Difficulty, Progress};
+use crate::systems::level::{Course, Level};
+use crate::systems::physics::{self, PhysicsBody};
+use crate::systems::pipegenerators::{Gap, PipeGenerator};
+use crate::systems::reachability::Envelope;
+use crate::systems::sprites::Sprites;
+use crate::systems::tuning::Tuning;
+
+/// The player's action for a single simulation step.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Input {
+    None,
+    Flap,
+}
+
+/// Something that happened during a step, for the view to react to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Event {
+    Flap,
+    Score,
+    PipeHit,
+    GroundHit,
+    /// The bird crossed the finish line of a level.
+    Finish,
+}
+
+/// All gameplay state of a run, advanced without any renderer or audio.
+pub struct Simulation {
+    pub bird: Bird,
+    pub ground: Ground,
+    pub pipes: Vec<PipeGroup>,
+    pub score: i32,
+    pub started: bool,
+    pub game_over: bool,
+    /// Whether the finish line of the level was crossed.
+    pub finished: bool,
+
+    tuning: Tuning,
+    seed: u64,
+    frame: u32,
+    flaps: u32,
+    /// Seconds since the first flap, while the bird is still alive.
+    run_time: f32,
+    pipe_spawner: PipeSpawner,
+    pipe_generator: Box<dyn PipeGenerator>,
+    pipe_geometry: PipeGeometry,
+    sprites: Sprites,
+    /// The level being played, if pipes come from one instead of the game mode.
+    course: Option<Course>,
+}
+
+impl Simulation {
+    /// Creates a run, with the bird's collision masks and the pipe size from `sprites`.
+    pub fn new(seed: u64, tuning: Tuning, sprites: &Sprites) -> Simulation {
+        let mut bird = Bird::new(&tuning, sprites.bird_masks.clone());
+        bird.reset();
+
+        Simulation {
+            bird,
+            ground: Ground::new(tuning.ground_y, tuning.screen_width as f32),
+            pipes: Vec::new(),
+            score: 0,
+            started: false,
+            game_over: false,
+            finished: false,
+            seed,
+            frame: 0,
+            flaps: 0,
+            run_time: 0.0,
+            pipe_spawner: PipeSpawner::new(tuning.spawn_interval),
+            pipe_generator: tuning.mode.pipe_generator(seed),
+            pipe_geometry: PipeGeometry::new(
+                sprites.pipes_size,
+                tuning.pipe_width,
+                tuning.pipe_gap,
+            ),
+            sprites: sprites.clone(),
+            course: None,
+            tuning,
+        }
+    }
+
+    /// Plays `level` to its finish line instead of endless pipes, starting `start`
+    /// pixels in.
+    pub fn play_level(&mut self, level: Level, start: f32) {
+        self.course = Some(Course::new(level, start));
+        self.reset(self.seed);
+    }
+
+    pub fn course(&self) -> Option<&Course> {
+        self.course.as_ref()
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn tuning(&self) -> &Tuning {
+        &self.tuning
+    }
+
+    pub fn sprites(&self) -> &Sprites {
+        &self.sprites
+    }
+
+    /// The difficulty at the current point of the run.
+    pub fn difficulty(&self) -> Difficulty {
+        self.tuning
+            .difficulty
+            .at(self.progress(), self.tuning.base_difficulty())
+    }
+
+    /// How many keyframes of the difficulty curve have been reached, starting at 1.
+    pub fn level(&self) -> usize {
+        self.tuning.difficulty.level(self.progress())
+    }
+
+    fn progress(&self) -> f32 {
+        match self.tuning.difficulty.progress {
+            Progress::Score => self.score as f32,
+            Progress::Time => self.run_time,
+        }
+    }
+
+    /// Applies the parts of `tuning` that can change mid-run: gravity, flap speed,
+    /// scroll speed and the interval between pipes. The last two only take effect
+    /// without difficulty keyframes, which set them otherwise.
+    pub fn retune(&mut self, tuning: &Tuning) {
+        self.tuning.gravity = tuning.gravity;
+        self.tuning.flap_speed = tuning.flap_speed;
+        self.tuning.scroll_speed = tuning.scroll_speed;
+        self.tuning.spawn_interval = tuning.spawn_interval;
+
+        self.bird.set_flap_speed(tuning.flap_speed);
+    }
+
+    /// The pipe groups the bird hasn't cleared yet, nearest first.
+    pub fn upcoming_pipes(&self) -> Vec<&PipeGroup> {
+        let bird_left = self.bird.position.x - BIRD_HALF_WIDTH;
+        let mut upcoming: Vec<&PipeGroup> = self
+            .pipes
+            .iter()
+            .filter(|pipe_group| {
+                pipe_group.alive && pipe_group.position.x + pipe_group.width() > bird_left
+            })
+            .collect();
+        upcoming.sort_by(|a, b| a.position.x.total_cmp(&b.position.x));
+        upcoming
+    }
+
+    pub fn pipe_spawner(&self) -> &PipeSpawner {
+        &self.pipe_spawner
+    }
+
+    /// The number of steps taken since the last reset.
+    pub fn frame(&self) -> u32 {
+        self.frame
+    }
+
+    /// The number of flaps since the last reset.
+    pub fn flaps(&self) -> u32 {
+        self.flaps
+    }
+
+    pub fn reset(&mut self, seed: u64) {
+        self.seed = seed;
+        self.pipe_spawner.reset();
+        self.pipe_generator.reset(seed);
+        self.frame = 0;
+        self.flaps = 0;
+        self.run_time = 0.0;
+        self.started = false;
+        self.pipes.clear();
+        self.ground.scroll = true;
+        self.bird.reset();
+        self.score = 0;
+        self.game_over = false;
+        self.finished = false;
+        if let Some(course) = &mut self.course {
+            course.restart();
+        }
+    }
+
+    pub fn step(&mut self, input: Input) -> Vec<Event> {
+        let mut events = Vec::new();
+        let dt = self.tuning.tick_length();
+        self.frame += 1;
+
+        self.bird.update(self.tuning.gravity, dt);
+
+        if input == Input::Flap {
+            if !self.started {
+                self.start();
+            }
+            if self.bird.alive && !self.game_over {
+                self.bird.flap();
+                self.flaps += 1;
+                events.push(Event::Flap);
+            }
+        }
+
+        if !self.game_over {
+            if self.started && self.bird.alive {
+                self.run_time += dt;
+            }
+            let difficulty = self.difficulty();
+            self.pipe_spawner
+                .set_spawn_interval(difficulty.spawn_interval);
+
+            for pipe_group in &mut self.pipes {
+                if !pipe_group.has_scored && pipe_group.gap_center().x <= self.bird.position.x {
+                    pipe_group.has_scored = true;
+                    self.score += 1;
+                    events.push(Event::Score);
+                }
+                pipe_group.update(difficulty.scroll_speed, dt);
+            }
+
+            self.ground.update(difficulty.scroll_speed, dt);
+
+            self.check_for_collisions(&mut events);
+
+            if self.course.is_some() {
+                self.follow_course(difficulty, dt, &mut events);
+            } else if self.pipe_spawner.should_spawn_pipe(dt) {
+                let envelope =
+                    Envelope::new(&self.tuning, &difficulty, self.pipe_geometry.pipe_width)
+                        .narrowed(self.tuning.pipe_motion.reach());
+                let gap = self.pipe_generator.next_gap(&difficulty, &envelope);
+                self.spawn_pipe(&gap, &difficulty, 0.0);
+            }
+        }
+
+        events
+    }
+
+    /// Starts the run like the first flap does, but without flapping.
+    pub fn start(&mut self) {
+        self.started = true;
+        self.bird.allow_gravity = true;
+
+        self.pipe_spawner.start();
+        for pipe_group in self.pipes.iter_mut().filter(|pipe_group| pipe_group.alive) {
+            pipe_group.enabled = true;
+        }
+    }
+
+    /// Spawns the pipe groups of the level that are due, and ends the run at its finish line.
+    fn follow_course(&mut self, difficulty: Difficulty, dt: f32, events: &mut Vec<Event>) {
+        let course = match &mut self.course {
+            Some(course) => course,
+            None => return,
+        };
+        if !self.bird.alive {
+            return;
+        }
+
+        // Groups from before the start are placed before the first flap, but the level
+        // only scrolls once the run has started.
+        if self.started {
+            course.advance(difficulty.scroll_speed * dt);
+        }
+        let mut due = Vec::new();
+        while let Some(pipe) = course.due_pipe() {
+            due.push(pipe);
+        }
+        let finish_x = course.finish_x(self.tuning.screen_width as f32, 1.0);
+
+        for (gap, overdue) in due {
+            self.spawn_pipe(&gap, &difficulty, overdue);
+        }
+
+        if finish_x <= self.bird.position.x {
+            events.push(Event::Finish);
+            self.finished = true;
+            self.game_over = true;
+            self.bird.allow_gravity = false;
+            self.ground.scroll = false;
+            for pipe_group in &mut self.pipes {
+                pipe_group.enabled = false;
+            }
+        }
+    }
+
+    /// Spawns a pipe group for `gap` at the right edge of the screen, or `behind` pixels
+    /// further left.
+    fn spawn_pipe(&mut self, gap: &Gap, difficulty: &Difficulty, behind: f32) {
+        let geometry = PipeGeometry {
+            gap_height: gap.height.unwrap_or(difficulty.pipe_gap),
+            ..self.pipe_geometry
+        };
+        let x = self.tuning.screen_width as f32 - behind;
+        let (y, motion) = (gap.offset, gap.motion.unwrap_or(self.tuning.pipe_motion));
+
+        let pipe_group = match self.pipes.iter().position(|pipe_group| !pipe_group.alive) {
+            Some(index) => &mut self.pipes[index],
+            None => {
+                self.pipes
+                    .push(PipeGroup::new(geometry, self.sprites.pipe_masks.clone()));
+                self.pipes.last_mut().unwrap()
+            }
+        };
+        pipe_group.set_geometry(geometry);
+        pipe_group.reset(x, y, motion);
+        // Groups placed before the first flap wait for it, like the bird.
+        pipe_group.enabled = self.started;
+    }
+
+    /// Sweeps the bird's last update against `target`, which moved by `target_motion`
+    /// over the same update. Returns the time of impact within the update, if any.
+    fn sweep_bird(
+        bird: &Bird,
+        target_motion: Vec2<f32>,
+        target: &mut dyn PhysicsBody,
+    ) -> Option<f32> {
+        let start = bird.previous_pose();
+        let end = bird.pose();
+        // Work in the targets' frame of reference, where only the bird moves.
+        let relative_start = Pose {
+            position: start.position + target_motion,
+            ..start
+        };
+
+        physics::time_of_impact(
+            end.position - relative_start.position,
+            |t| bird.shapes_at(Pose::lerp(relative_start, end, t)),
+            &target.get_collision_rect(),
+            &target.get_shapes(),
+        )
+    }
+
+    fn check_for_collisions(&mut self, events: &mut Vec<Event>) {
+        if self.bird.alive {
+            let bird = &self.bird;
+            // Each pipe is swept on its own, as moving gaps can open or close.
+            let impact = self
+                .pipes
+                .iter_mut()
+                .filter(|pipe_group| pipe_group.alive)
+                .flat_map(|pipe_group| pipe_group.pipes_mut())
+                .filter_map(|pipe| Simulation::sweep_bird(bird, pipe.motion(), pipe))
+                .fold(None, |first: Option<f32>, t| {
+                    Some(first.map_or(t, |first| first.min(t)))
+                });
+
+            if let Some(t) = impact {
+                self.bird.rewind(t);
+                for pipe_group in &mut self.pipes {
+                    pipe_group.rewind(t);
+                }
+
+                events.push(Event::PipeHit);
+                self.bird.kill();
+
+                self.pipe_spawner.stop();
+                self.ground.scroll = false;
+
+                for pipe_group in &mut self.pipes {
+                    pipe_group.enabled = false;
+                }
+            }
+        }
+
+        let ground_impact = if self.game_over {
+            None
+        } else {
+            Simulation::sweep_bird(&self.bird, Vec2::zero(), &mut self.ground)
+        };
+
+        if let Some(t) = ground_impact {
+            self.bird.rewind(t);
+
+            events.push(Event::GroundHit);
+            self.bird.kill();
+            self.bird.allow_gravity = false;
+            self.ground.scroll = false;
+
+            self.game_over = true;
+            self.pipe_spawner.stop();
+
+            for pipe_group in &mut self.pipes {
+                pipe_group.enabled = false;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::systems::autopilot::Autopilot;
+    use crate::systems::controls::InputSource;
+
+    const SEED: u64 = 42;
+
+    fn simulation(seed: u64) -> Simulation {
+        Simulation::new(seed, Tuning::default(), &Sprites::solid())
+    }
+
+    /// Lets the autopilot fly `steps` steps, and returns every event with its step.
+    fn fly(simulation: &mut Simulation, steps: u32) -> Vec<(u32, Event)> {
+        let mut autopilot = Autopilot::new();
+        let mut events = Vec::new();
+        for _ in 0..steps {
+            let input = autopilot.next_input(simulation);
+            for event in simulation.step(input) {
+                events.push((simulation.frame(), event));
+            }
+        }
+        events
+    }
+
+    #[test]
+    fn waits_for_the_first_flap() {
+        let mut simulation = simulation(SEED);
+        let start = simulation.bird.position;
+        for _ in 0..60 {
+            assert!(simulation.step(Input::None).is_empty());
+        }
+        assert!(!simulation.started);
+        assert_eq!(simulation.bird.position, start);
+        assert!(simulation.pipes.is_empty());
+
+        assert_eq!(simulation.step(Input::Flap), vec![Event::Flap]);
+        assert!(simulation.started);
+    }
+
+    #[test]
+    fn falls_to_the_ground_without_flapping() {
+        let mut simulation = simulation(SEED);
+        let mut events = simulation.step(Input::Flap);
+        for _ in 0..600 {
+            events.extend(simulation.step(Input::None));
+        }
+
+        assert_eq!(events, vec![Event::Flap, Event::GroundHit]);
+        assert!(simulation.game_over);
+        assert!(!simulation.bird.alive);
+        assert_eq!(simulation.score, 0);
+    }
+
+    #[test]
+    fn reports_a_score_event_per_point() {
+        let mut simulation = simulation(SEED);
+        let events = fly(&mut simulation, 1200);
+        let scores = events
+            .iter()
+            .filter(|(_, event)| *event == Event::Score)
+            .count();
+
+        assert!(simulation.score > 0);
+        assert_eq!(scores, simulation.score as usize);
+        assert_eq!(events.first(), Some(&(1, Event::Flap)));
+    }
+
+    #[test]
+    fn same_seed_and_inputs_give_the_same_run() {
+        let mut first = simulation(SEED);
+        let mut second = simulation(SEED);
+        let first_events = fly(&mut first, 1200);
+        let second_events = fly(&mut second, 1200);
+
+        assert_eq!(first_events, second_events);
+        assert_eq!(first.bird.pose(), second.bird.pose());
+        assert_eq!(first.bird.velocity(), second.bird.velocity());
+        assert_eq!((first.score, first.flaps()), (second.score, second.flaps()));
+        let gaps = |simulation: &Simulation| {
+            simulation
+                .pipes
+                .iter()
+                .map(PipeGroup::gap_center)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(gaps(&first), gaps(&second));
+    }
+
+    #[test]
+    fn other_seeds_give_other_pipes() {
+        let mut first = simulation(SEED);
+        let mut second = simulation(SEED + 1);
+        fly(&mut first, 300);
+        fly(&mut second, 300);
+
+        let heights = |simulation: &Simulation| {
+            simulation
+                .pipes
+                .iter()
+                .map(|pipe_group| pipe_group.gap_center().y)
+                .collect::<Vec<_>>()
+        };
+        assert_ne!(heights(&first), heights(&second));
+    }
+}
@@ -0,0 +1,56 @@
+use std::sync::Arc;
+
+use tetra::graphics::{ImageData, Rectangle};
+use tetra::math::Vec2;
+
+use crate::prefabs::bird::Bird;
+use crate::systems::physics::CollisionMask;
+
+/// What gameplay needs to know about the sprites: which of their pixels are solid, and
+/// how big they are. A `Simulation` is handed these instead of reading any files.
+#[derive(Clone)]
+pub struct Sprites {
+    /// One mask per frame of the flapping animation in `bird.png`.
+    pub bird_masks: Vec<Arc<CollisionMask>>,
+    /// The top and the bottom pipe in `pipes.png`.
+    pub pipe_masks: [Arc<CollisionMask>; 2],
+    /// Size of `pipes.png`, which holds the top and bottom pipe side by side.
+    pub pipes_size: Vec2<f32>,
+}
+
+impl Sprites {
+    pub fn from_images(bird: &ImageData, pipes: &ImageData) -> Sprites {
+        let pipe_frame = Vec2::new(pipes.width() as f32 / 2.0, pipes.height() as f32);
+        let pipe_mask = |index: f32| {
+            let region = Rectangle::new(index * pipe_frame.x, 0.0, pipe_frame.x, pipe_frame.y);
+            Arc::new(CollisionMask::from_image(pipes, region))
+        };
+
+        Sprites {
+            bird_masks: Bird::frames()
+                .iter()
+                .map(|frame| Arc::new(CollisionMask::from_image(bird, *frame)))
+                .collect(),
+            pipe_masks: [pipe_mask(0.0), pipe_mask(1.0)],
+            pipes_size: Vec2::new(pipes.width() as f32, pipes.height() as f32),
+        }
+    }
+
+    /// Reads `./resources/bird.png` and `./resources/pipes.png`.
+    pub fn load() -> tetra::Result<Sprites> {
+        Ok(Sprites::from_images(
+            &ImageData::from_file("./resources/bird.png")?,
+            &ImageData::from_file("./resources/pipes.png")?,
+        ))
+    }
+
+    /// Sprites the size of the real ones with every pixel solid, for tests that shouldn't
+    /// depend on the files.
+    #[cfg(test)]
+    pub fn solid() -> Sprites {
+        let image = |width: i32, height: i32| {
+            ImageData::from_rgba8(width, height, vec![255; (width * height * 4) as usize]).unwrap()
+        };
+        Sprites::from_images(&image(102, 24), &image(108, 320))
+    }
+}
@@ -1,15 +1,184 @@
+use std::collections::BTreeMap;
 use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
 
-use crate::FILE_NAME;
+use tetra::math::Vec2;
 
-pub fn write(high_score: i32) -> std::io::Result<()> {
-    fs::write(FILE_NAME, high_score.to_string())?;
+use crate::prefabs::bird::Pose;
+use crate::systems::replay::Replay;
+use crate::{CAMPAIGN_FILE_NAME, REPLAY_FILE_NAME};
+
+const GHOST_MAGIC: &[u8; 4] = b"FLPG";
+const GHOST_VERSION: u16 = 1;
+/// Magic, version and tick rate.
+const GHOST_HEADER_LENGTH: usize = 10;
+/// Position and rotation, as three `f32`s.
+const POSE_LENGTH: usize = 12;
+
+pub fn write(path: &str, high_score: i32) -> std::io::Result<()> {
+    fs::write(path, high_score.to_string())?;
     Ok(())
 }
 
-pub fn read() -> std::io::Result<i32> {
-    match fs::read_to_string(FILE_NAME) {
+pub fn read(path: &str) -> std::io::Result<i32> {
+    match fs::read_to_string(path) {
         Ok(content) => Ok(content.parse().unwrap()),
         Err(_err) => Ok(0),
     }
 }
+
+pub fn write_replay(replay: &Replay) -> std::io::Result<()> {
+    fs::write(REPLAY_FILE_NAME, replay.to_bytes())?;
+    Ok(())
+}
+
+/// The last recorded run, or `None` if no run was recorded yet.
+pub fn read_replay() -> std::io::Result<Option<Replay>> {
+    match fs::read(REPLAY_FILE_NAME) {
+        Ok(bytes) => Replay::from_bytes(&bytes)
+            .map(Some)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err)),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+/// The ghost belongs to the highscore in `highscore_file`, so it is kept next to it
+/// with the extension swapped for `.ghost`.
+pub fn ghost_path(highscore_file: &str) -> PathBuf {
+    Path::new(highscore_file).with_extension("ghost")
+}
+
+/// Writes the poses of a run simulated at `tick_rate` steps per second, as the ghost
+/// for the highscore in `highscore_file`.
+pub fn write_ghost(highscore_file: &str, trace: &[Pose], tick_rate: f32) -> std::io::Result<()> {
+    fs::write(ghost_path(highscore_file), ghost_to_bytes(trace, tick_rate))?;
+    Ok(())
+}
+
+/// The poses of the highscore run, or none if there is no ghost yet. A ghost recorded
+/// at another tick rate than `tick_rate` would drift away from the pipes, so it is an
+/// error.
+pub fn read_ghost(highscore_file: &str, tick_rate: f32) -> std::io::Result<Vec<Pose>> {
+    match fs::read(ghost_path(highscore_file)) {
+        Ok(bytes) => ghost_from_bytes(&bytes, tick_rate)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err)),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(err) => Err(err),
+    }
+}
+
+/// Encodes a ghost as a header with the tick rate, followed by one pose per step.
+fn ghost_to_bytes(trace: &[Pose], tick_rate: f32) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(GHOST_HEADER_LENGTH + trace.len() * POSE_LENGTH);
+    bytes.extend_from_slice(GHOST_MAGIC);
+    bytes.extend_from_slice(&GHOST_VERSION.to_le_bytes());
+    bytes.extend_from_slice(&tick_rate.to_le_bytes());
+    for pose in trace {
+        bytes.extend_from_slice(&pose.position.x.to_le_bytes());
+        bytes.extend_from_slice(&pose.position.y.to_le_bytes());
+        bytes.extend_from_slice(&pose.rotation.to_le_bytes());
+    }
+    bytes
+}
+
+fn ghost_from_bytes(bytes: &[u8], tick_rate: f32) -> Result<Vec<Pose>, String> {
+    let value = |bytes: &[u8], i: usize| {
+        f32::from_le_bytes([bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]])
+    };
+
+    if bytes.len() < GHOST_HEADER_LENGTH || &bytes[..4] != GHOST_MAGIC {
+        return Err("not a ghost file".to_string());
+    }
+    let version = u16::from_le_bytes([bytes[4], bytes[5]]);
+    if version != GHOST_VERSION {
+        return Err(format!("unsupported ghost version {}", version));
+    }
+    let recorded_tick_rate = value(bytes, 6);
+    if recorded_tick_rate != tick_rate {
+        return Err(format!(
+            "ghost recorded at {} ticks per second instead of {}",
+            recorded_tick_rate, tick_rate
+        ));
+    }
+
+    let poses = &bytes[GHOST_HEADER_LENGTH..];
+    if !poses.len().is_multiple_of(POSE_LENGTH) {
+        return Err("unexpected end of ghost".to_string());
+    }
+    Ok(poses
+        .chunks_exact(POSE_LENGTH)
+        .map(|chunk| Pose {
+            position: Vec2::new(value(chunk, 0), value(chunk, 4)),
+            rotation: value(chunk, 8),
+        })
+        .collect())
+}
+
+/// Writes the best stars earned per level, as `id stars` lines.
+pub fn write_campaign(stars: &BTreeMap<String, u32>) -> std::io::Result<()> {
+    let content: String = stars
+        .iter()
+        .map(|(id, stars)| format!("{} {}\n", id, stars))
+        .collect();
+    fs::write(CAMPAIGN_FILE_NAME, content)?;
+    Ok(())
+}
+
+/// The most stars earned per level id, or none if no level was finished yet.
+pub fn read_campaign() -> std::io::Result<BTreeMap<String, u32>> {
+    match fs::read_to_string(CAMPAIGN_FILE_NAME) {
+        Ok(content) => Ok(content
+            .lines()
+            .filter_map(|line| {
+                let (id, stars) = line.rsplit_once(' ')?;
+                Some((id.to_string(), stars.parse().ok()?))
+            })
+            .collect()),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+        Err(err) => Err(err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trace() -> Vec<Pose> {
+        (0..3)
+            .map(|i| Pose {
+                position: Vec2::new(100.0, 250.0 - i as f32),
+                rotation: -0.5 * i as f32,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn ghosts_round_trip() {
+        let bytes = ghost_to_bytes(&trace(), 60.0);
+        assert_eq!(ghost_from_bytes(&bytes, 60.0), Ok(trace()));
+    }
+
+    #[test]
+    fn rejects_ghosts_from_another_tick_rate() {
+        let bytes = ghost_to_bytes(&trace(), 120.0);
+        assert!(ghost_from_bytes(&bytes, 60.0).is_err());
+    }
+
+    #[test]
+    fn rejects_ghosts_without_a_header() {
+        let mut bytes = ghost_to_bytes(&trace(), 60.0);
+        assert!(ghost_from_bytes(&bytes[GHOST_HEADER_LENGTH..], 60.0).is_err());
+
+        bytes[4] += 1;
+        assert!(ghost_from_bytes(&bytes, 60.0).is_err());
+    }
+
+    #[test]
+    fn keeps_each_ghost_next_to_its_highscore() {
+        assert_eq!(ghost_path("highscore.txt"), Path::new("highscore.ghost"));
+        assert_eq!(ghost_path("saves/hard.txt"), Path::new("saves/hard.ghost"));
+        assert_eq!(ghost_path("scores"), Path::new("scores.ghost"));
+    }
+}
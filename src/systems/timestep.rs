@@ -0,0 +1,106 @@
+use std::time::Duration;
+
+/// The most ticks run in one frame, so a long stall doesn't snowball.
+const MAX_TICKS_PER_FRAME: u32 = 8;
+
+/// Splits variable frame times into a whole number of fixed-length ticks.
+pub struct FixedTimestep {
+    tick_length: f64,
+    accumulator: f64,
+}
+
+impl FixedTimestep {
+    pub fn new(tick_rate: f32) -> FixedTimestep {
+        FixedTimestep {
+            tick_length: 1.0 / f64::from(tick_rate),
+            accumulator: 0.0,
+        }
+    }
+
+    /// Adds a frame's worth of time and returns how many ticks are now due.
+    pub fn advance(&mut self, delta: Duration) -> u32 {
+        let max_accumulator = self.tick_length * f64::from(MAX_TICKS_PER_FRAME);
+        self.accumulator = (self.accumulator + delta.as_secs_f64()).min(max_accumulator);
+
+        let mut ticks = 0;
+        while self.accumulator >= self.tick_length {
+            self.accumulator -= self.tick_length;
+            ticks += 1;
+        }
+        ticks
+    }
+
+    /// How far the next tick has progressed, from 0.0 to 1.0, for interpolating draws.
+    pub fn blend_factor(&self) -> f32 {
+        (self.accumulator / self.tick_length) as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TICK_RATE: f32 = 60.0;
+
+    fn millis(milliseconds: u64) -> Duration {
+        Duration::from_millis(milliseconds)
+    }
+
+    #[test]
+    fn runs_one_tick_per_tick_length() {
+        let mut timestep = FixedTimestep::new(TICK_RATE);
+        let total: u32 = (0..60)
+            .map(|_| timestep.advance(Duration::from_secs_f64(1.0 / 60.0)))
+            .sum();
+        assert!((59..=60).contains(&total));
+
+        let mut timestep = FixedTimestep::new(TICK_RATE);
+        assert_eq!(timestep.advance(millis(50)), 3);
+    }
+
+    #[test]
+    fn carries_leftover_time_to_the_next_frame() {
+        let mut timestep = FixedTimestep::new(TICK_RATE);
+        assert_eq!(timestep.advance(millis(10)), 0);
+        assert_eq!(timestep.advance(millis(10)), 1);
+        assert_eq!(timestep.advance(millis(10)), 0);
+        assert_eq!(timestep.advance(millis(10)), 1);
+    }
+
+    #[test]
+    fn runs_several_ticks_on_slow_frames() {
+        let mut timestep = FixedTimestep::new(TICK_RATE);
+        assert_eq!(timestep.advance(millis(100)), 6);
+    }
+
+    #[test]
+    fn limits_the_ticks_after_a_stall() {
+        let mut timestep = FixedTimestep::new(TICK_RATE);
+        assert_eq!(
+            timestep.advance(Duration::from_secs(5)),
+            MAX_TICKS_PER_FRAME
+        );
+        // The time beyond the limit is dropped rather than caught up on later.
+        assert_eq!(timestep.advance(Duration::ZERO), 0);
+        assert_eq!(timestep.advance(millis(17)), 1);
+    }
+
+    #[test]
+    fn blend_factor_stays_below_one() {
+        let mut timestep = FixedTimestep::new(TICK_RATE);
+        assert_eq!(timestep.blend_factor(), 0.0);
+
+        for milliseconds in [1, 7, 16, 17, 33, 250, 5000] {
+            timestep.advance(millis(milliseconds));
+            let blend = timestep.blend_factor();
+            assert!((0.0..1.0).contains(&blend), "blend factor {}", blend);
+        }
+    }
+
+    #[test]
+    fn blend_factor_follows_the_time_into_the_next_tick() {
+        let mut timestep = FixedTimestep::new(TICK_RATE);
+        timestep.advance(Duration::from_secs_f64(1.5 / 60.0));
+        assert!((timestep.blend_factor() - 0.5).abs() < 1e-4);
+    }
+}
@@ -0,0 +1,149 @@
+use std::f32::consts::TAU;
+
+use tetra::graphics::mesh::{IndexBuffer, Mesh, Vertex, VertexBuffer};
+use tetra::graphics::{self, Canvas, Color, DrawParams, StencilAction, StencilState, StencilTest};
+use tetra::math::Vec2;
+use tetra::window;
+use tetra::Context;
+
+/// Points around the edge of the circle of `Effect::Wipe`.
+const WIPE_SEGMENTS: u32 = 48;
+
+/// How one scene gives way to the next.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Effect {
+    /// The old screen fades to black, then the new one fades in.
+    FadeToBlack,
+    /// The new screen fades in over the old one.
+    Crossfade,
+    /// The new screen pushes the old one out to the left, like the pipes scroll.
+    Slide,
+    /// The new screen shows through a circle growing from the middle.
+    Wipe,
+}
+
+/// An effect being played between a snapshot of the old screen and the live new one.
+pub struct ActiveEffect {
+    effect: Effect,
+    duration: f32,
+    elapsed: f32,
+    outgoing: Canvas,
+    /// A circle with a radius of one for `Effect::Wipe`, scaled up as it plays.
+    circle: Option<Mesh>,
+}
+
+impl ActiveEffect {
+    /// Starts `effect` from a snapshot of what `draw_outgoing` draws. Without a
+    /// duration there is nothing to play, so nothing is drawn and `None` is returned.
+    pub fn new(
+        ctx: &mut Context,
+        effect: Effect,
+        duration: f32,
+        draw_outgoing: impl FnOnce(&mut Context) -> tetra::Result,
+    ) -> tetra::Result<Option<ActiveEffect>> {
+        if duration <= 0.0 {
+            return Ok(None);
+        }
+
+        let (width, height) = window::get_size(ctx);
+        let outgoing = Canvas::new(ctx, width, height)?;
+        graphics::set_canvas(ctx, &outgoing);
+        graphics::clear(ctx, Color::BLACK);
+        let drawn = draw_outgoing(ctx);
+        graphics::reset_canvas(ctx);
+        drawn?;
+
+        let circle = match effect {
+            Effect::Wipe => Some(ActiveEffect::unit_circle(ctx)?),
+            _ => None,
+        };
+
+        Ok(Some(ActiveEffect {
+            effect,
+            duration,
+            elapsed: 0.0,
+            outgoing,
+            circle,
+        }))
+    }
+
+    /// Advances the effect by `dt` seconds, and returns whether it has finished.
+    pub fn update(&mut self, dt: f32) -> bool {
+        self.elapsed += dt;
+        self.elapsed >= self.duration
+    }
+
+    /// Draws the effect, with the new screen drawn to `incoming`.
+    pub fn draw(&self, ctx: &mut Context, incoming: &Canvas) -> tetra::Result {
+        let linear = (self.elapsed / self.duration).clamp(0.0, 1.0);
+        let t = linear * linear * (3.0 - 2.0 * linear);
+        let (width, height) = (incoming.width() as f32, incoming.height() as f32);
+
+        match self.effect {
+            Effect::FadeToBlack => {
+                let (canvas, brightness) = if t < 0.5 {
+                    (&self.outgoing, 1.0 - t * 2.0)
+                } else {
+                    (incoming, t * 2.0 - 1.0)
+                };
+                canvas.draw(
+                    ctx,
+                    DrawParams::new().color(Color::rgb(brightness, brightness, brightness)),
+                );
+            }
+            Effect::Crossfade => {
+                self.outgoing.draw(ctx, Vec2::zero());
+                incoming.draw(ctx, DrawParams::new().color(Color::rgba(1.0, 1.0, 1.0, t)));
+            }
+            Effect::Slide => {
+                self.outgoing.draw(ctx, Vec2::new(-t * width, 0.0));
+                incoming.draw(ctx, Vec2::new((1.0 - t) * width, 0.0));
+            }
+            Effect::Wipe => {
+                self.outgoing.draw(ctx, Vec2::zero());
+                let center = Vec2::new(width, height) / 2.0;
+                let radius = t * center.magnitude();
+
+                // The circle marks where the new screen shows, without being drawn itself.
+                graphics::clear_stencil(ctx, 0);
+                graphics::set_stencil_state(ctx, StencilState::write(StencilAction::Replace, 1));
+                graphics::set_color_mask(ctx, false, false, false, false);
+                if let Some(circle) = &self.circle {
+                    circle.draw(
+                        ctx,
+                        DrawParams::new()
+                            .position(center)
+                            .scale(Vec2::broadcast(radius)),
+                    );
+                }
+                graphics::set_color_mask(ctx, true, true, true, true);
+                graphics::set_stencil_state(ctx, StencilState::read(StencilTest::EqualTo, 1));
+                incoming.draw(ctx, Vec2::zero());
+                graphics::set_stencil_state(ctx, StencilState::disabled());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// A circle around the origin with a radius of one, to be scaled when drawn.
+    fn unit_circle(ctx: &mut Context) -> tetra::Result<Mesh> {
+        let vertex = |position: Vec2<f32>| Vertex::new(position, Vec2::zero(), Color::WHITE);
+
+        let mut vertices = vec![vertex(Vec2::zero())];
+        for index in 0..WIPE_SEGMENTS {
+            let angle = index as f32 * TAU / WIPE_SEGMENTS as f32;
+            vertices.push(vertex(Vec2::new(angle.cos(), angle.sin())));
+        }
+        let indices: Vec<u32> = (1..=WIPE_SEGMENTS)
+            .flat_map(|index| [0, index, index % WIPE_SEGMENTS + 1])
+            .collect();
+
+        let mut mesh = Mesh::indexed(
+            VertexBuffer::new(ctx, &vertices)?,
+            IndexBuffer::new(ctx, &indices)?,
+        );
+        mesh.set_backface_culling(false);
+        Ok(mesh)
+    }
+}
@@ -0,0 +1,87 @@
+use serde::Deserialize;
+
+use crate::prefabs::bird::Hitbox;
+use crate::prefabs::pipes::PipeMotion;
+use crate::systems::config::{positive, size};
+use crate::systems::difficulty::{Difficulty, DifficultyCurve};
+use crate::systems::gamemode::GameMode;
+use crate::{GRAVITY, SCREEN_HEIGHT, SCREEN_WIDTH, SCROLL_SPEED, TICK_RATE};
+
+/// The constants a run is simulated with.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Tuning {
+    #[serde(deserialize_with = "size")]
+    pub screen_width: i32,
+    #[serde(deserialize_with = "size")]
+    pub screen_height: i32,
+    #[serde(deserialize_with = "positive")]
+    pub gravity: f32,
+    #[serde(deserialize_with = "positive")]
+    pub scroll_speed: f32,
+    #[serde(deserialize_with = "positive")]
+    pub tick_rate: f32,
+    /// Upward speed after a flap, in pixels per second.
+    #[serde(deserialize_with = "positive")]
+    pub flap_speed: f32,
+    /// Time between two pipe groups, in seconds.
+    #[serde(deserialize_with = "positive")]
+    pub spawn_interval: f32,
+    /// How far the gap may be placed above or below its usual height, in pixels.
+    #[serde(deserialize_with = "positive")]
+    pub gap_offset_range: f32,
+    /// Distance from the top of the screen to the top of the ground, in pixels.
+    #[serde(deserialize_with = "positive")]
+    pub ground_y: f32,
+    pub bird_hitbox: Hitbox,
+    /// Vertical space between the top and the bottom pipe, in pixels.
+    #[serde(deserialize_with = "positive")]
+    pub pipe_gap: f32,
+    #[serde(deserialize_with = "positive")]
+    pub pipe_width: f32,
+    /// How spawn interval, pipe gap, gap offset range and scroll speed change over a run.
+    pub difficulty: DifficultyCurve,
+    /// Decides the pipe layout.
+    pub mode: GameMode,
+    /// How pipe groups move unless their gap says otherwise.
+    pub pipe_motion: PipeMotion,
+}
+
+impl Default for Tuning {
+    fn default() -> Tuning {
+        Tuning {
+            screen_width: SCREEN_WIDTH,
+            screen_height: SCREEN_HEIGHT,
+            gravity: GRAVITY,
+            scroll_speed: SCROLL_SPEED,
+            tick_rate: TICK_RATE,
+            flap_speed: 390.0,
+            spawn_interval: 80.0 / 60.0,
+            gap_offset_range: 100.0,
+            ground_y: 400.0,
+            bird_hitbox: Hitbox::Mask,
+            pipe_gap: 120.0,
+            pipe_width: 54.0,
+            difficulty: DifficultyCurve::default(),
+            mode: GameMode::Classic,
+            pipe_motion: PipeMotion::default(),
+        }
+    }
+}
+
+impl Tuning {
+    /// The length of one simulation step, in seconds.
+    pub fn tick_length(&self) -> f32 {
+        1.0 / self.tick_rate
+    }
+
+    /// The difficulty settings used when the curve has no keyframes.
+    pub fn base_difficulty(&self) -> Difficulty {
+        Difficulty {
+            spawn_interval: self.spawn_interval,
+            pipe_gap: self.pipe_gap,
+            gap_offset_range: self.gap_offset_range,
+            scroll_speed: self.scroll_speed,
+        }
+    }
+}